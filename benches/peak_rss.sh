@@ -0,0 +1,41 @@
+#!/bin/sh
+# Peak resident memory of plotxy on a large generated table, read from a file and from STDIN,
+# and drawn as points colored by category, which styles every row.
+#
+# usage: benches/peak_rss.sh [ROWS] [PLOTXY]
+# ROWS defaults to 5000000 rows of 8 columns, PLOTXY to target/release/plotxy
+set -e
+
+ROWS=${1:-5000000}
+PLOTXY=${2:-target/release/plotxy}
+DIR=$(mktemp -d)
+trap 'rm -rf "$DIR"' EXIT
+
+awk -v rows="$ROWS" 'BEGIN {
+    srand(1)
+    print "x\ty\ta\tb\tc\td\te\tlabel"
+    for (i = 0; i < rows; i++)
+        printf "%d\t%.6f\t%.6f\t%.6f\t%.6f\t%.6f\t%.6f\tsample%d\n", i, rand(), rand(), rand(), rand(), rand(), rand(), i % 100
+}' > "$DIR/large.tsv"
+echo "input: $(du -h "$DIR/large.tsv" | cut -f1), $ROWS rows"
+
+# VmHWM is the peak resident set size of a running process, gone once it exited
+peak()
+{
+    peak=0
+    while hwm=$(awk '/^VmHWM/ { print $2 }' "/proc/$1/status" 2>/dev/null) && [ -n "$hwm" ]
+    do
+        peak=$hwm
+        sleep 0.05
+    done
+    echo "$((peak / 1024)) MiB"
+}
+
+"$PLOTXY" -H -x x -y y --shape heat2d -o "$DIR/file.png" "$DIR/large.tsv" > /dev/null &
+echo "file:  $(peak $!)"
+
+"$PLOTXY" -H -x x -y y --shape heat2d -o "$DIR/stdin.png" < "$DIR/large.tsv" > /dev/null &
+echo "stdin: $(peak $!)"
+
+"$PLOTXY" -H -x x -y y --shape circle -c label -o "$DIR/points.png" "$DIR/large.tsv" > /dev/null &
+echo "points: $(peak $!)"
@@ -0,0 +1,258 @@
+//! Aggregation of many Y values per X before plotting.
+
+use crate::PlotError;
+
+use polars::prelude::*;
+use std::str::FromStr;
+
+/// Reduces the Y values sharing an X value, and color facet category, to one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate
+{
+    Mean,
+    Median,
+    Sum,
+    /// number of Y values present
+    Count,
+    Min,
+    Max,
+}
+
+impl Aggregate
+{
+    pub fn name(self) -> &'static str
+    {
+        match self
+        {
+            Aggregate::Mean => "mean",
+            Aggregate::Median => "median",
+            Aggregate::Sum => "sum",
+            Aggregate::Count => "count",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+        }
+    }
+}
+
+impl FromStr for Aggregate
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "mean" => Ok(Aggregate::Mean),
+            "median" => Ok(Aggregate::Median),
+            "sum" => Ok(Aggregate::Sum),
+            "count" => Ok(Aggregate::Count),
+            "min" => Ok(Aggregate::Min),
+            "max" => Ok(Aggregate::Max),
+            _ => Err(format!(
+                "unknown aggregation {}, expected mean, median, sum, count, min or max",
+                s
+            )),
+        }
+    }
+}
+
+/// Spread drawn around an aggregated value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorBars
+{
+    /// one sample standard deviation above and below
+    StdDev,
+    /// smallest to largest value
+    MinMax,
+}
+
+impl FromStr for ErrorBars
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "std" => Ok(ErrorBars::StdDev),
+            "minmax" => Ok(ErrorBars::MinMax),
+            _ => Err(format!("unknown error bars {}, expected std or minmax", s)),
+        }
+    }
+}
+
+/// One row per X value and group, ordered by group and X
+pub struct Aggregated
+{
+    pub x: Series,
+    pub y: Series,
+    /// group of each row when grouped
+    pub groups: Option<Vec<usize>>,
+    /// lower and upper end of the error bar of each row
+    pub bounds: Option<(Series, Series)>,
+}
+
+/// Groups `y` by `x` and `groups` via polars lazy, rows with a missing X are dropped
+pub fn aggregate(
+    x: &Series,
+    y: &Series,
+    groups: Option<&[usize]>,
+    aggregate: Aggregate,
+    error_bars: Option<ErrorBars>,
+) -> Result<Aggregated, PlotError>
+{
+    let group: UInt32Chunked = match groups
+    {
+        Some(groups) => groups.iter().map(|&g| Some(g as u32)).collect(),
+        None => (0..x.len()).map(|_| Some(0u32)).collect(),
+    };
+    let frame = DataFrame::new(vec![
+        x.cast(&DataType::Float64)?.with_name("x".into()).into(),
+        y.cast(&DataType::Float64)?.with_name("y".into()).into(),
+        group.with_name("group".into()).into_series().into(),
+    ])?;
+
+    let value = col("y");
+    let mut aggregations = vec![match aggregate
+    {
+        Aggregate::Mean => value.clone().mean(),
+        Aggregate::Median => value.clone().median(),
+        Aggregate::Sum => value.clone().sum(),
+        Aggregate::Count => value.clone().count().cast(DataType::Float64),
+        Aggregate::Min => value.clone().min(),
+        Aggregate::Max => value.clone().max(),
+    }
+    .alias("y")];
+    match error_bars
+    {
+        Some(ErrorBars::StdDev) =>
+        {
+            // around the aggregated value, a single value has no spread
+            let std = value.clone().std(1).fill_null(lit(0.0));
+            aggregations.push(std.alias("spread"));
+        }
+        Some(ErrorBars::MinMax) =>
+        {
+            aggregations.push(value.clone().min().alias("low"));
+            aggregations.push(value.max().alias("high"));
+        }
+        None => (),
+    }
+
+    let mut aggregated = frame
+        .lazy()
+        .filter(col("x").is_not_null())
+        .group_by([col("group"), col("x")])
+        .agg(aggregations)
+        .sort(["group", "x"], Default::default());
+    if error_bars == Some(ErrorBars::StdDev)
+    {
+        aggregated = aggregated.with_columns([
+            (col("y") - col("spread")).alias("low"),
+            (col("y") + col("spread")).alias("high"),
+        ]);
+    }
+    let aggregated = aggregated.collect()?;
+
+    let column = |name: &str| -> Result<Series, PlotError> {
+        Ok(aggregated.column(name)?.as_materialized_series().clone())
+    };
+    Ok(Aggregated {
+        x: column("x")?,
+        y: column("y")?,
+        groups: match groups
+        {
+            Some(_) => Some(
+                aggregated
+                    .column("group")?
+                    .u32()?
+                    .into_iter()
+                    .map(|g| g.unwrap_or(0) as usize)
+                    .collect(),
+            ),
+            None => None,
+        },
+        bounds: match error_bars
+        {
+            Some(_) => Some((column("low")?, column("high")?)),
+            None => None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn values(series: &Series) -> Vec<Option<f64>>
+    {
+        series.f64().unwrap().into_iter().collect()
+    }
+
+    /// Three values at X 1, one at 2, only missing ones at 3 and a row without X
+    fn sample() -> (Series, Series)
+    {
+        (
+            Series::new("x".into(), [Some(1), Some(1), Some(1), Some(2), Some(2), Some(3), None]),
+            Series::new(
+                "y".into(),
+                [
+                    Some(1.0),
+                    Some(2.0),
+                    Some(6.0),
+                    Some(4.0),
+                    None,
+                    None,
+                    Some(9.0),
+                ],
+            ),
+        )
+    }
+
+    #[test]
+    fn aggregates_per_x()
+    {
+        let (x, y) = sample();
+        let mean = aggregate(&x, &y, None, Aggregate::Mean, None).unwrap();
+        assert_eq!(values(&mean.x), [Some(1.0), Some(2.0), Some(3.0)]);
+        assert_eq!(values(&mean.y), [Some(3.0), Some(4.0), None]);
+        assert!(mean.groups.is_none() && mean.bounds.is_none());
+
+        let median = aggregate(&x, &y, None, Aggregate::Median, None).unwrap();
+        assert_eq!(values(&median.y), [Some(2.0), Some(4.0), None]);
+        let count = aggregate(&x, &y, None, Aggregate::Count, None).unwrap();
+        assert_eq!(values(&count.y), [Some(3.0), Some(1.0), Some(0.0)]);
+        let max = aggregate(&x, &y, None, Aggregate::Max, None).unwrap();
+        assert_eq!(values(&max.y), [Some(6.0), Some(4.0), None]);
+    }
+
+    #[test]
+    fn aggregates_per_group()
+    {
+        let (x, y) = sample();
+        let groups = [0, 1, 0, 0, 1, 1, 1];
+        let sum = aggregate(&x, &y, Some(&groups), Aggregate::Sum, None).unwrap();
+        // ordered by group, then X
+        assert_eq!(sum.groups.unwrap(), [0, 0, 1, 1, 1]);
+        assert_eq!(values(&sum.x), [Some(1.0), Some(2.0), Some(1.0), Some(2.0), Some(3.0)]);
+        assert_eq!(values(&sum.y), [Some(7.0), Some(4.0), Some(2.0), Some(0.0), Some(0.0)]);
+    }
+
+    #[test]
+    fn error_bars()
+    {
+        let (x, y) = sample();
+        let std = aggregate(&x, &y, None, Aggregate::Mean, Some(ErrorBars::StdDev)).unwrap();
+        let (low, high) = std.bounds.unwrap();
+        // 1, 2 and 6 have the sample variance 7, a single value no spread
+        let spread = 7f64.sqrt();
+        assert_eq!(values(&low), [Some(3.0 - spread), Some(4.0), None]);
+        assert_eq!(values(&high), [Some(3.0 + spread), Some(4.0), None]);
+
+        let minmax = aggregate(&x, &y, None, Aggregate::Median, Some(ErrorBars::MinMax)).unwrap();
+        let (low, high) = minmax.bounds.unwrap();
+        assert_eq!(values(&low), [Some(1.0), Some(4.0), None]);
+        assert_eq!(values(&high), [Some(6.0), Some(4.0), None]);
+    }
+}
@@ -0,0 +1,292 @@
+use std::ops::Range;
+use std::str::FromStr;
+
+/// How an axis range is derived from the data when not given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AxisRange
+{
+    /// nice range, extended to zero when the data comes close to it or columns need a base
+    #[default]
+    Auto,
+    /// exactly the smallest to the largest value
+    Data,
+    /// padded and rounded outward to a tick step
+    Nice,
+}
+
+impl FromStr for AxisRange
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "auto" => Ok(AxisRange::Auto),
+            "data" => Ok(AxisRange::Data),
+            "nice" => Ok(AxisRange::Nice),
+            _ => Err(format!("unknown axis range {}, expected auto, data or nice", s)),
+        }
+    }
+}
+
+/// Axis range for values from `min` to `max`.
+///
+/// On a logarithmic axis `min` is the smallest positive value and ranges round to tenths
+/// of a decade. `include_zero` extends an auto range to zero, e.g. as base of columns.
+pub fn auto_range(min: f64, max: f64, mode: AxisRange, log: bool, include_zero: bool)
+    -> Range<f64>
+{
+    if log
+    {
+        // a single value gets a decade around it
+        let (min, max) = if max > min
+        {
+            (min, max)
+        }
+        else
+        {
+            (min / 3.0, max * 3.0)
+        };
+        return match mode
+        {
+            AxisRange::Data => min..max,
+            // padding keeps the smallest value, e.g. a count of 1, off the border
+            AxisRange::Auto | AxisRange::Nice =>
+            {
+                10f64.powf(((min / 1.02).log10() * 10.0).floor() / 10.0)..next_potence(max * 1.02)
+            }
+        };
+    }
+
+    let (mut min, mut max) = (min, max);
+    if mode == AxisRange::Auto
+    {
+        // zero belongs on the axis when the data covers more than half the way to it
+        if include_zero || (min > 0.0 && min <= max / 2.0)
+        {
+            min = min.min(0.0);
+        }
+        if include_zero || (max < 0.0 && max >= min / 2.0)
+        {
+            max = max.max(0.0);
+        }
+    }
+    if max <= min
+    {
+        let half = if min == 0.0 { 1.0 } else { min.abs() / 10.0 };
+        (min, max) = (min - half, max + half);
+    }
+    match mode
+    {
+        AxisRange::Data => min..max,
+        AxisRange::Auto | AxisRange::Nice =>
+        {
+            // padding keeps points off the border, zero stays the border
+            let step = nice_step(max - min, 10);
+            let padding = (max - min) * 0.02;
+            let lower = if min == 0.0
+            {
+                0.0
+            }
+            else
+            {
+                ((min - padding) / step).floor() * step
+            };
+            let upper = if max == 0.0
+            {
+                0.0
+            }
+            else
+            {
+                ((max + padding) / step).ceil() * step
+            };
+            lower..upper
+        }
+    }
+}
+
+/// Rounds up to the next tenth of a decade, used as default axis maximum
+pub fn next_potence(x: f64) -> f64
+{
+    10f64.powf(((x.log10() * 10f64).ceil()) / 10.0)
+}
+
+/// Step of 1, 2 or 5 times a power of ten that divides `range` into about `count` ticks
+pub fn nice_step(range: f64, count: usize) -> f64
+{
+    let raw = range / count.max(1) as f64;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let fraction = raw / magnitude;
+    let nice = if fraction <= 1.0
+    {
+        1.0
+    }
+    else if fraction <= 2.0
+    {
+        2.0
+    }
+    else if fraction <= 5.0
+    {
+        5.0
+    }
+    else
+    {
+        10.0
+    };
+    nice * magnitude
+}
+
+/// Formats a number compactly, whole numbers without decimals
+pub fn format_number(value: f64) -> String
+{
+    let abs_value = value.abs();
+    if value == value.trunc() && abs_value < 1e15
+    {
+        format!("{}", value as i64)
+    }
+    else if (1e-3..1e9).contains(&abs_value)
+    {
+        let formatted = format!("{:.3}", value);
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+    else
+    {
+        format!("{:.2e}", value)
+    }
+}
+
+/// Formats a number with an SI prefix (K, M, G, ...) for axis labels
+pub fn format_si_number(value: f64) -> String
+{
+    let abs_value = value.abs();
+
+    if abs_value >= 1e12
+    {
+        format!("{:.2}T", value / 1e12)
+    }
+    else if abs_value >= 1e9
+    {
+        format!("{:.2}G", value / 1e9)
+    }
+    else if abs_value >= 1e6
+    {
+        format!("{:.2}M", value / 1e6)
+    }
+    else if abs_value >= 1e3
+    {
+        format!("{:.2}K", value / 1e3)
+    }
+    else if abs_value >= 1.0
+    {
+        format!("{:.2}", value)
+    }
+    else if abs_value >= 1e-3
+    {
+        format!("{:.2}m", value * 1e3)
+    }
+    else if abs_value >= 1e-6
+    {
+        format!("{:.2}μ", value * 1e6)
+    }
+    else if abs_value >= 1e-9
+    {
+        format!("{:.2}n", value * 1e9)
+    }
+    else if abs_value >= 1e-12
+    {
+        format!("{:.2}p", value * 1e12)
+    }
+    else if abs_value == 0.0
+    {
+        "0".to_string()
+    }
+    else
+    {
+        format!("{:.2e}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn assert_range(range: Range<f64>, start: f64, end: f64)
+    {
+        assert!(
+            (range.start - start).abs() < 1e-9 && (range.end - end).abs() < 1e-9,
+            "{:?} is not {}..{}",
+            range,
+            start,
+            end
+        );
+    }
+
+    #[test]
+    fn negative_ranges()
+    {
+        assert_range(auto_range(-10.0, -6.0, AxisRange::Auto, false, false), -10.5, -5.5);
+        assert_range(auto_range(-10.0, -6.0, AxisRange::Data, false, false), -10.0, -6.0);
+        // close enough to zero to include it, which stays the border
+        assert_range(auto_range(-10.0, -4.0, AxisRange::Auto, false, false), -11.0, 0.0);
+        assert_range(auto_range(-10.0, -4.0, AxisRange::Nice, false, false), -11.0, -3.0);
+    }
+
+    #[test]
+    fn single_values()
+    {
+        assert_range(auto_range(5.0, 5.0, AxisRange::Data, false, false), 4.5, 5.5);
+        assert_range(auto_range(5.0, 5.0, AxisRange::Auto, false, false), 4.4, 5.6);
+        assert_range(auto_range(0.0, 0.0, AxisRange::Auto, false, false), -1.2, 1.2);
+        assert_range(auto_range(10.0, 10.0, AxisRange::Data, true, false), 10.0 / 3.0, 30.0);
+    }
+
+    #[test]
+    fn ranges_across_zero()
+    {
+        assert_range(auto_range(-3.0, 7.0, AxisRange::Auto, false, false), -4.0, 8.0);
+        assert_range(auto_range(-3.0, 7.0, AxisRange::Data, false, false), -3.0, 7.0);
+        // columns grow from zero
+        assert_range(auto_range(2.0, 7.0, AxisRange::Auto, false, true), 0.0, 8.0);
+        assert_range(auto_range(2.0, 7.0, AxisRange::Auto, false, false), 0.0, 8.0);
+        assert_range(auto_range(6.0, 7.0, AxisRange::Auto, false, false), 5.9, 7.1);
+    }
+
+    #[test]
+    fn logarithmic_ranges()
+    {
+        // tenths of a decade beyond the padded data
+        assert_range(
+            auto_range(1.0, 1000.0, AxisRange::Auto, true, false),
+            10f64.powf(-0.1),
+            10f64.powf(3.1),
+        );
+        assert_range(auto_range(1.0, 1000.0, AxisRange::Data, true, false), 1.0, 1000.0);
+        assert!((next_potence(20.0) - 10f64.powf(1.4)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn steps_and_labels()
+    {
+        assert!((nice_step(1.0, 5) - 0.2).abs() < 1e-12);
+        assert!((nice_step(7.0, 10) - 1.0).abs() < 1e-12);
+        assert!((nice_step(250.0, 10) - 50.0).abs() < 1e-12);
+        assert!((nice_step(0.03, 3) - 0.01).abs() < 1e-12);
+
+        assert_eq!(format_si_number(1500.0), "1.50K");
+        assert_eq!(format_si_number(-2.5e6), "-2.50M");
+        assert_eq!(format_si_number(5.0), "5.00");
+        assert_eq!(format_si_number(0.002), "2.00m");
+        assert_eq!(format_si_number(0.0), "0");
+        assert_eq!(format_si_number(1e-15), "1.00e-15");
+
+        assert_eq!(format_number(-7.0), "-7");
+        assert_eq!(format_number(2.5), "2.5");
+        assert_eq!(format_number(0.125), "0.125");
+        assert_eq!(format_number(1e-5), "1.00e-5");
+    }
+}
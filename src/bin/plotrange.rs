@@ -0,0 +1,521 @@
+use plotters::prelude::*;
+
+use plotxy::axis::format_si_number;
+use plotxy::color::{CategoryOrder, Facet, GradientScale, GradientScheme, Palette};
+use plotxy::input::InputFormat;
+use plotxy::{color, expr, input, PlotError};
+
+use polars::prelude::*;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+#[allow(non_snake_case)]
+#[derive(Debug, Parser)]
+#[command(
+    name = "plotrange",
+    about = "Plots intervals as horizontal bars",
+    version
+)]
+struct Opt
+{
+    #[arg(value_name = "FILE")]
+    /// optional file with on entry per line, may be gzip, zstd or bzip2 compressed [default: STDIN]
+    input: Option<PathBuf>,
+
+    #[arg(long, short = 'b', default_value = "1")]
+    /// column name or index to be used as interval start
+    start: String,
+
+    #[arg(long, short, default_value = "2")]
+    /// column name or index to be used as interval end
+    end: String,
+
+    #[arg(long, short = 'L')]
+    /// column name or index to be used as lane, default packs intervals into rows without overlap
+    lane: Option<String>,
+
+    #[arg(long, short, default_value = "0.6")]
+    /// transparency channel
+    alpha: f64,
+
+    #[arg(long, short, default_value = "1E88E5")]
+    /// default plot color
+    plot_color: String,
+
+    #[arg(long, short)]
+    /// column name or index to be used as color facet
+    color: Option<String>,
+
+    #[arg(long)]
+    /// column name or index to be used as color gradient facet
+    gradient: Option<String>,
+
+    #[arg(long, default_value = "palette99")]
+    /// colors for color facet categories: palette99, tableau10, set1, set2, set3, dark2, paired, pastel1, accent or a hex list like 1E88E5,D81B60
+    palette: Palette,
+
+    #[arg(long, default_value = "sorted")]
+    /// order in which color facet categories get palette colors: sorted or appearance
+    color_order: CategoryOrder,
+
+    #[arg(long)]
+    /// fixed category colors like ok=2E7D32,fail=C62828, or a file with one category=color per line
+    color_map: Option<String>,
+
+    #[arg(long, default_value = "yellow,red")]
+    /// gradient colors: a preset like viridis, magma, turbo or rdbu, a color list like white,1E88E5 or a GIMP .ggr file
+    gradient_scheme: GradientScheme,
+
+    #[arg(long)]
+    /// value at the start of the gradient, default smallest value
+    gradient_min: Option<f64>,
+
+    #[arg(long)]
+    /// value at the end of the gradient, default largest value
+    gradient_max: Option<f64>,
+
+    #[arg(long)]
+    /// map gradient values on a logarithmic scale
+    gradient_log: bool,
+
+    #[arg(short, long)]
+    /// column delimiter: a character or string, tab, comma, space, semicolon, pipe, or whitespace
+    /// for runs of blanks, default detected from the first lines
+    delimiter: Option<String>,
+
+    #[arg(long)]
+    /// input format: csv, tsv, parquet, ipc, ndjson or json, default detected from the file
+    format: Option<InputFormat>,
+
+    #[arg(short = 'H', long)]
+    /// input has header line (see also --skip)
+    Header: bool,
+
+    #[arg(long, short, default_value = "0")]
+    /// skip lines before header
+    skip: usize,
+
+    #[arg(long, value_name = "EXPR")]
+    /// keep only rows where this holds, e.g. "col3 > 10 && status == 'ok'", may be repeated
+    filter: Vec<String>,
+
+    #[arg(long)]
+    /// minimum X dimension, default smallest start
+    x_dim_min: Option<f64>,
+
+    #[arg(long)]
+    /// maximum X dimension, default largest end
+    x_dim_max: Option<f64>,
+
+    #[arg(long, default_value = "0.8")]
+    /// bar height as fraction of the lane height
+    bar_height: f64,
+
+    #[arg(long, short, value_name = "FILE")]
+    /// file to save PNG plot to, default append .plotrange.png to input filename
+    outfile: Option<PathBuf>,
+
+    #[arg(long)]
+    /// set output format to svg
+    svg: bool,
+
+    #[arg(short, long)]
+    /// title above the plot, default filename
+    title: Option<String>,
+
+    #[arg(long, default_value = "2560")]
+    /// image width
+    width: u32,
+
+    #[arg(long, default_value = "1200")]
+    /// image height
+    height: u32,
+
+    #[arg(long, default_value = "Position")]
+    /// x-axis label
+    xdesc: String,
+
+    #[arg(long, default_value = "Lane")]
+    /// y-axis label
+    ydesc: String,
+
+    #[arg(long, default_value = "70")]
+    /// x-axis label area size
+    xdesc_area: u32,
+
+    #[arg(long, default_value = "200")]
+    /// y-axis label area size
+    ydesc_area: u32,
+
+    #[arg(long, default_value = "sans-serif")]
+    /// label font name
+    label_font: String,
+
+    #[arg(long, default_value = "24")]
+    /// label font size
+    label_font_size: u32,
+
+    #[arg(long, default_value = "sans-serif")]
+    /// axis description font name
+    axis_desc_font: String,
+
+    #[arg(long, default_value = "22")]
+    /// axis description font size
+    axis_desc_font_size: u32,
+
+    #[arg(long, default_value = "sans-serif")]
+    /// title font name
+    title_font: String,
+
+    #[arg(long, default_value = "24")]
+    /// title font size
+    title_font_size: u32,
+
+    #[arg(long)]
+    /// use SI number formatting for X-axis labels (K, M, G, etc.)
+    si_format_x: bool,
+}
+
+/// An interval ready for drawing, `lane` indexes into the lane names
+struct Interval
+{
+    start: f64,
+    end: f64,
+    lane: usize,
+    style: ShapeStyle,
+}
+
+fn main() -> Result<(), PlotError>
+{
+    let mut opt = Opt::parse();
+
+    let delimiter = opt
+        .delimiter
+        .as_deref()
+        .map(input::parse_delimiter)
+        .transpose()?;
+    let mut references = vec![opt.start.as_str(), opt.end.as_str()];
+    references.extend(
+        [&opt.lane, &opt.color, &opt.gradient]
+            .into_iter()
+            .filter_map(|reference| reference.as_deref()),
+    );
+    references.extend(opt.filter.iter().map(String::as_str));
+    let df = input::read_columns(
+        opt.input.as_deref(),
+        opt.format,
+        delimiter,
+        opt.Header,
+        opt.skip,
+        Some(&references),
+    )?;
+    let df = expr::filter_rows(df, &opt.filter)?;
+
+    if opt.input.is_none()
+    {
+        opt.input = Some(std::path::Path::new("STDIN").to_path_buf());
+    }
+
+    if opt.outfile.is_none()
+    {
+        let input = opt
+            .input
+            .as_ref()
+            .ok_or_else(|| PlotError::InvalidData("Input path missing".to_string()))?;
+        opt.outfile = Some(input::default_outfile(
+            input,
+            if opt.svg
+            {
+                ".plotrange.svg"
+            }
+            else
+            {
+                ".plotrange.png"
+            },
+        )?);
+    }
+
+    plot_range(&opt, df)
+}
+
+fn plot_range(opt: &Opt, df: DataFrame) -> Result<(), PlotError>
+{
+    let plot_filename = opt
+        .outfile
+        .as_ref()
+        .ok_or_else(|| PlotError::InvalidData("Output file path missing".to_string()))?
+        .to_str()
+        .ok_or_else(|| PlotError::InvalidData("Invalid output file path".to_string()))?
+        .to_string();
+
+    println!("{}", plot_filename);
+
+    let (intervals, lanes) = make_intervals(opt, &df)?;
+
+    if opt.svg
+    {
+        plot_on_backend(
+            opt,
+            &plot_filename,
+            &intervals,
+            &lanes,
+            SVGBackend::new(&plot_filename, (opt.width, opt.height)),
+        )?;
+    }
+    else
+    {
+        plot_on_backend(
+            opt,
+            &plot_filename,
+            &intervals,
+            &lanes,
+            BitMapBackend::new(&plot_filename, (opt.width, opt.height)),
+        )?;
+    }
+    Ok(())
+}
+
+/// Collects the intervals with their style and assigns each to a lane
+fn make_intervals(opt: &Opt, df: &DataFrame) -> Result<(Vec<Interval>, Vec<String>), PlotError>
+{
+    let start = plotxy::column_index(df, &opt.start, "Start")?;
+    let end = plotxy::column_index(df, &opt.end, "End")?;
+    let start = plotxy::column(df, start, "Start")?.cast(&DataType::Float64)?;
+    let end = plotxy::column(df, end, "End")?.cast(&DataType::Float64)?;
+    let start = start
+        .f64()
+        .map_err(|_| PlotError::InvalidData("Start column is not numeric".to_string()))?;
+    let end = end
+        .f64()
+        .map_err(|_| PlotError::InvalidData("End column is not numeric".to_string()))?;
+
+    // colored like in plotxy, by color facet category, else by gradient value
+    let facet = opt
+        .color
+        .as_ref()
+        .map(|color_facet| {
+            let color_facet_index = plotxy::column_index(df, color_facet, "Color")?;
+            let color_map = opt
+                .color_map
+                .as_deref()
+                .map(color::parse_color_map)
+                .transpose()?
+                .unwrap_or_default();
+            Facet::new(
+                plotxy::column(df, color_facet_index, "Color")?,
+                &opt.palette,
+                opt.color_order,
+                &color_map,
+            )
+        })
+        .transpose()?;
+    let gradient = match (&facet, &opt.gradient)
+    {
+        (None, Some(color_gradient)) =>
+        {
+            let color_gradient_index = plotxy::column_index(df, color_gradient, "Gradient")?;
+            let gradient_series = plotxy::column(df, color_gradient_index, "Gradient")?;
+            let scale = GradientScale::new(
+                gradient_series,
+                &opt.gradient_scheme,
+                (opt.gradient_min, opt.gradient_max),
+                opt.gradient_log,
+            )?;
+            let values = gradient_series.cast(&DataType::Float64)?;
+            let values = values
+                .f64()
+                .map_err(|_| PlotError::InvalidData("Gradient column is not numeric".to_string()))?
+                .clone();
+            Some((values, scale))
+        }
+        _ => None,
+    };
+    let plot_color = color::parse_hex_color(&opt.plot_color)?;
+    let style = |row: usize| {
+        let color = match (&facet, &gradient)
+        {
+            (Some(facet), _) => facet.categories[facet.indices[row]].1.mix(opt.alpha),
+            (None, Some((values, scale))) =>
+            {
+                scale.color_at(values.get(row).unwrap_or(0.0), opt.alpha)
+            }
+            (None, None) => plot_color.mix(opt.alpha),
+        };
+        color.filled()
+    };
+
+    let lane_values = match &opt.lane
+    {
+        Some(lane) =>
+        {
+            let lane_index = plotxy::column_index(df, lane, "Lane")?;
+            Some(plotxy::column(df, lane_index, "Lane")?.cast(&DataType::String)?)
+        }
+        None => None,
+    };
+    let lane_values = lane_values
+        .as_ref()
+        .map(|s| s.str())
+        .transpose()
+        .map_err(|_| PlotError::InvalidData("Lane column is not string".to_string()))?;
+
+    let mut intervals = Vec::with_capacity(df.height());
+    let mut lanes: Vec<String> = Vec::new();
+    let mut skipped = 0;
+    for (row, (s, e)) in start.into_iter().zip(end).enumerate()
+    {
+        let (Some(s), Some(e)) = (s, e)
+        else
+        {
+            skipped += 1;
+            continue;
+        };
+        // reverse intervals, e.g. on the minus strand, are drawn like forward ones
+        let (start, end) = if s <= e { (s, e) } else { (e, s) };
+
+        let lane = match &lane_values
+        {
+            Some(values) =>
+            {
+                let name = values.get(row).unwrap_or("NA");
+                match lanes.iter().position(|l| l == name)
+                {
+                    Some(lane) => lane,
+                    None =>
+                    {
+                        lanes.push(name.to_string());
+                        lanes.len() - 1
+                    }
+                }
+            }
+            None => 0,
+        };
+        intervals.push(Interval {
+            start,
+            end,
+            lane,
+            style: style(row),
+        });
+    }
+    if skipped > 0
+    {
+        eprintln!("Warning: {} intervals without start or end skipped", skipped);
+    }
+
+    if lane_values.is_none()
+    {
+        lanes = pack_intervals(&mut intervals);
+    }
+
+    if intervals.is_empty()
+    {
+        return Err(PlotError::InvalidData("No intervals to plot".to_string()));
+    }
+    Ok((intervals, lanes))
+}
+
+/// Greedily assigns intervals to the first row where they do not overlap, returns the row names
+fn pack_intervals(intervals: &mut [Interval]) -> Vec<String>
+{
+    intervals.sort_by(|a, b| a.start.total_cmp(&b.start));
+    let mut row_ends: Vec<f64> = Vec::new();
+    for interval in intervals.iter_mut()
+    {
+        interval.lane = match row_ends.iter().position(|&end| end <= interval.start)
+        {
+            Some(row) => row,
+            None =>
+            {
+                row_ends.push(interval.start);
+                row_ends.len() - 1
+            }
+        };
+        row_ends[interval.lane] = interval.end;
+    }
+    (1..=row_ends.len()).map(|row| row.to_string()).collect()
+}
+
+fn plot_on_backend<B>(
+    opt: &Opt,
+    plot_filename: &str,
+    intervals: &[Interval],
+    lanes: &[String],
+    backend: B,
+) -> Result<(), PlotError>
+where
+    B: DrawingBackend,
+{
+    let root = backend.into_drawing_area();
+    root.fill(&WHITE)
+        .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+    let root = root
+        .titled(
+            opt.title.as_deref().unwrap_or(plot_filename),
+            (opt.title_font.as_str(), opt.title_font_size),
+        )
+        .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+
+    let x_min = intervals
+        .iter()
+        .map(|i| i.start)
+        .fold(f64::INFINITY, f64::min);
+    let x_max = intervals
+        .iter()
+        .map(|i| i.end)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let padding = ((x_max - x_min) * 0.02).max(0.5);
+    let x_dim_min = opt.x_dim_min.unwrap_or(x_min - padding);
+    let x_dim_max = opt.x_dim_max.unwrap_or(x_max + padding);
+
+    // first lane on top, lanes sit on whole numbers
+    let number_of_lanes = lanes.len();
+    let lane_y = |lane: usize| (number_of_lanes - 1 - lane) as f64;
+
+    let mut grid = ChartBuilder::on(&root)
+        .x_label_area_size(opt.xdesc_area)
+        .y_label_area_size(opt.ydesc_area)
+        .margin(26u32)
+        .build_cartesian_2d(x_dim_min..x_dim_max, -0.5..number_of_lanes as f64 - 0.5)
+        .map_err(|e| PlotError::InvalidData(format!("Grid creation error: {}", e)))?;
+
+    let si_formatter = |x: &f64| format_si_number(*x);
+    let lane_formatter = |y: &f64| {
+        let row = y.round() as usize;
+        if (y - y.round()).abs() < 1e-6 && row < number_of_lanes
+        {
+            lanes[number_of_lanes - 1 - row].clone()
+        }
+        else
+        {
+            String::new()
+        }
+    };
+
+    let mut mesh = grid.configure_mesh();
+    mesh.disable_y_mesh()
+        .bold_line_style(WHITE.mix(0.3))
+        .y_desc(&opt.ydesc)
+        .x_desc(&opt.xdesc)
+        .label_style((opt.label_font.as_str(), opt.label_font_size))
+        .axis_desc_style((opt.axis_desc_font.as_str(), opt.axis_desc_font_size))
+        .y_labels(number_of_lanes)
+        .y_label_formatter(&lane_formatter);
+    if opt.si_format_x
+    {
+        mesh.x_label_formatter(&si_formatter);
+    }
+    mesh.draw()
+        .map_err(|e| PlotError::InvalidData(format!("Draw error: {}", e)))?;
+
+    let half_height = opt.bar_height.clamp(0.0, 1.0) / 2.0;
+    grid.draw_series(intervals.iter().map(|i| {
+        let y = lane_y(i.lane);
+        Rectangle::new([(i.start, y - half_height), (i.end, y + half_height)], i.style)
+    }))
+    .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+
+    root.present()
+        .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+    Ok(())
+}
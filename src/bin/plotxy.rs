@@ -1,65 +1,19 @@
-use colorgrad::Gradient;
-
-use plotters::chart::ChartBuilder;
-use plotters::element::{Drawable, PointCollection};
-use plotters::prelude::*;
-
-use polars::prelude::*;
-use std::error::Error;
-use std::io::Cursor;
-use std::iter::Zip;
+use plotxy::aggregate::{Aggregate, ErrorBars};
+use plotxy::axis::AxisRange;
+use plotxy::category::XOrder;
+use plotxy::color::{CategoryOrder, GradientScheme, Palette};
+use plotxy::fit::Fit;
+use plotxy::hist::{Bins, Histogram};
+use plotxy::input::{Delimiter, InputFormat};
+use plotxy::panel::FacetScales;
+use plotxy::time::TimeParse;
+use plotxy::{
+    color, expr, input, ErrorColumns, LegendPosition, OutputFormat, PlotError, PlotSpec, Shape,
+};
+
+use polars::prelude::{DataFrame, Series};
 use std::path::PathBuf;
 
-#[derive(Debug)]
-enum PlotError
-{
-    IoError(std::io::Error),
-    PolarsError(PolarsError),
-    HexDecodeError(hex::FromHexError),
-    InvalidColumn(String),
-    InvalidData(String),
-}
-
-impl std::fmt::Display for PlotError
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
-    {
-        match self
-        {
-            PlotError::IoError(e) => write!(f, "IO error: {}", e),
-            PlotError::PolarsError(e) => write!(f, "Data processing error: {}", e),
-            PlotError::HexDecodeError(e) => write!(f, "Invalid color format: {}", e),
-            PlotError::InvalidColumn(msg) => write!(f, "Invalid column: {}", msg),
-            PlotError::InvalidData(msg) => write!(f, "Invalid data: {}", msg),
-        }
-    }
-}
-
-impl Error for PlotError {}
-
-impl From<std::io::Error> for PlotError
-{
-    fn from(error: std::io::Error) -> Self
-    {
-        PlotError::IoError(error)
-    }
-}
-
-impl From<PolarsError> for PlotError
-{
-    fn from(error: PolarsError) -> Self
-    {
-        PlotError::PolarsError(error)
-    }
-}
-
-impl From<hex::FromHexError> for PlotError
-{
-    fn from(error: hex::FromHexError) -> Self
-    {
-        PlotError::HexDecodeError(error)
-    }
-}
 use clap::Parser;
 
 #[allow(non_snake_case)]
@@ -68,12 +22,12 @@ use clap::Parser;
 struct Opt
 {
     #[arg(value_name = "FILE")]
-    /// optional file with on entry per line [default: STDIN]
-    input: Option<PathBuf>,
+    /// optional files with one entry per line, may be gzip, zstd or bzip2 compressed, several are overlaid colored by file [default: STDIN]
+    input: Vec<PathBuf>,
 
     #[arg(long, short, default_value = "1")]
-    /// column index to be used as X
-    x: usize,
+    /// column name, index or expression like "bytes / seconds" to be used as X, 0 for the row number
+    x: String,
 
     #[arg(long, short, default_value = "0.3")]
     /// transparency channel
@@ -84,21 +38,69 @@ struct Opt
     plot_color: String,
 
     #[arg(long, short, default_value = "2")]
-    /// column index to be used as Y
-    y: usize,
+    /// column names, indices or expressions to be used as Y, one series each, e.g. 2,3 or 2-5 or latency,log2(fold_change)
+    y: String,
 
     #[arg(long, short)]
-    /// column index to be used as color facet
-    color: Option<usize>,
+    /// column name, index or expression to be used as color facet
+    color: Option<String>,
+
+    #[arg(long)]
+    /// column name or index to split into small multiples, one panel per value
+    facet: Option<String>,
+
+    #[arg(long)]
+    /// rows of the facet panel grid, default about square
+    facet_rows: Option<usize>,
+
+    #[arg(long)]
+    /// columns of the facet panel grid, default about square
+    facet_columns: Option<usize>,
+
+    #[arg(long, default_value = "shared")]
+    /// axes the facet panels have in common: shared, free-x, free-y or free
+    facet_scales: FacetScales,
+
+    #[arg(long)]
+    /// column name or index to be used as color gradient facet
+    gradient: Option<String>,
+
+    #[arg(long, default_value = "palette99")]
+    /// colors for series and color facet categories: palette99, tableau10, set1, set2, set3, dark2, paired, pastel1, accent or a hex list like 1E88E5,D81B60
+    palette: Palette,
+
+    #[arg(long, default_value = "sorted")]
+    /// order in which color facet categories get palette colors: sorted or appearance
+    color_order: CategoryOrder,
 
     #[arg(long)]
-    /// column index to be used as color gradient facet
-    gradient: Option<usize>,
+    /// fixed category colors like ok=2E7D32,fail=C62828, or a file with one category=color per line
+    color_map: Option<String>,
 
-    // r"" makes it printable as escaped in default
-    #[arg(short, long, default_value = r"\t")]
-    /// column delimiter
-    delimiter: String,
+    #[arg(long, default_value = "yellow,red")]
+    /// gradient colors: a preset like viridis, magma, turbo or rdbu, a color list like white,1E88E5 or a GIMP .ggr file
+    gradient_scheme: GradientScheme,
+
+    #[arg(long)]
+    /// value at the start of the gradient, default smallest value
+    gradient_min: Option<f64>,
+
+    #[arg(long)]
+    /// value at the end of the gradient, default largest value
+    gradient_max: Option<f64>,
+
+    #[arg(long)]
+    /// map gradient values on a logarithmic scale
+    gradient_log: bool,
+
+    #[arg(short, long)]
+    /// column delimiter: a character or string, tab, comma, space, semicolon, pipe, or whitespace
+    /// for runs of blanks, default detected from the first lines
+    delimiter: Option<String>,
+
+    #[arg(long)]
+    /// input format: csv, tsv, parquet, ipc, ndjson or json, default detected from the file
+    format: Option<InputFormat>,
 
     #[arg(short = 'H', long)]
     /// input has header line (see also --skip)
@@ -108,6 +110,10 @@ struct Opt
     /// skip lines before header
     skip: usize,
 
+    #[arg(long, value_name = "EXPR")]
+    /// keep only rows where this holds, e.g. "col3 > 10 && status == 'ok'", may be repeated
+    filter: Vec<String>,
+
     #[arg(long, short)]
     /// plot logarithmic X-axis
     logx: bool,
@@ -116,22 +122,30 @@ struct Opt
     /// plot logarithmic Y-axis
     logy: bool,
 
-    #[arg(long, default_value = "0.0")]
-    /// minimum X dimension
-    x_dim_min: f64,
+    #[arg(long)]
+    /// minimum X dimension, default from --x-range
+    x_dim_min: Option<f64>,
 
     #[arg(long)]
-    /// maximum X dimension
+    /// maximum X dimension, default from --x-range
     x_dim_max: Option<f64>,
 
-    #[arg(long, default_value = "0.0")]
-    /// minimum Y dimension
-    y_dim_min: f64,
+    #[arg(long)]
+    /// minimum Y dimension, default from --y-range
+    y_dim_min: Option<f64>,
 
     #[arg(long)]
-    /// maximum Y dimension
+    /// maximum Y dimension, default from --y-range
     y_dim_max: Option<f64>,
 
+    #[arg(long, default_value = "auto")]
+    /// automatic X range: auto (nice, extended to zero when close), data (exact) or nice (padded and rounded)
+    x_range: AxisRange,
+
+    #[arg(long, default_value = "auto")]
+    /// automatic Y range: auto (nice, extended to zero when close or for columns), data or nice
+    y_range: AxisRange,
+
     #[arg(long, short, value_name = "FILE")]
     /// file to save PNG plot to, default append .plotxy.png to input filename
     outfile: Option<PathBuf>,
@@ -149,16 +163,32 @@ struct Opt
     width: u32,
 
     #[arg(long, default_value = "1200")]
-    /// image width
+    /// image height, multiplied by the number of panel rows with --facet
     height: u32,
 
-    #[arg(long, default_value = "X")]
-    /// x-axis label
-    xdesc: String,
+    #[arg(long, value_name = "PARSE", num_args = 0..=1, require_equals = true, default_missing_value = "auto")]
+    /// X is a date/time: auto, epoch-s, epoch-ms, epoch-ns or a strftime format, e.g. --x-time=%d/%m/%Y
+    x_time: Option<TimeParse>,
 
-    #[arg(long, default_value = "Y")]
-    /// y-axis label
-    ydesc: String,
+    #[arg(long)]
+    /// strftime format for time axis labels, default depends on the time span
+    time_format: Option<String>,
+
+    #[arg(long, value_name = "ORDER", num_args = 0..=1, require_equals = true, default_missing_value = "input")]
+    /// X is categorical, one slot per value ordered by input, alphabetical or y, default for string columns
+    x_categories: Option<XOrder>,
+
+    #[arg(long)]
+    /// rotate X-axis labels, e.g. for long category names
+    rotate_x_labels: bool,
+
+    #[arg(long)]
+    /// x-axis label, default X column name
+    xdesc: Option<String>,
+
+    #[arg(long)]
+    /// y-axis label, default Y column names
+    ydesc: Option<String>,
 
     #[arg(long, default_value = "70")]
     /// x-axis label area size
@@ -197,8 +227,96 @@ struct Opt
     point_size: u32,
 
     #[arg(long, default_value = "circle")]
-    /// plotting shape: circle, column
-    shape: String,
+    /// plotting shape: circle, column, line, line+points, or hexbin and heat2d cells colored by count with the gradient scheme
+    shape: Shape,
+
+    #[arg(long, default_value = "50")]
+    /// hexbin and heat2d cells along the X range
+    cells: usize,
+
+    #[arg(long)]
+    /// hexbin and heat2d cell width in X units, overrides --cells, in decades with --logx
+    cell_width: Option<f64>,
+
+    #[arg(long)]
+    /// hexbin and heat2d cell height in Y units, default about as high as wide, in decades with --logy
+    cell_height: Option<f64>,
+
+    #[arg(long)]
+    /// connect line points sorted by X instead of in input order
+    sort_x: bool,
+
+    #[arg(long, default_value = "2")]
+    /// line width for line shapes
+    line_width: u32,
+
+    #[arg(long)]
+    /// histogram of the X column, per color facet category if given
+    hist: bool,
+
+    #[arg(long, default_value = "fd")]
+    /// histogram bins: a count, fd (Freedman-Diaconis) or sturges
+    bins: Bins,
+
+    #[arg(long)]
+    /// histogram bin width, overrides --bins, in decades with --log-bins
+    bin_width: Option<f64>,
+
+    #[arg(long)]
+    /// logarithmically spaced histogram bins
+    log_bins: bool,
+
+    #[arg(long)]
+    /// histogram densities instead of counts
+    density: bool,
+
+    #[arg(long)]
+    /// stack histograms of color facet categories instead of overlaying them
+    stack: bool,
+
+    #[arg(long, conflicts_with = "hist")]
+    /// plot one Y value per X value and color facet category: mean, median, sum, count, min or max
+    aggregate: Option<Aggregate>,
+
+    #[arg(long, requires = "aggregate")]
+    /// error bars around aggregated values: std (standard deviation) or minmax
+    error_bars: Option<ErrorBars>,
+
+    #[arg(long, conflicts_with_all = ["xlow", "xhigh"])]
+    /// column name or index with the X error, drawn from X - error to X + error
+    xerr: Option<String>,
+
+    #[arg(long)]
+    /// column name or index with the lower end of X error bars
+    xlow: Option<String>,
+
+    #[arg(long)]
+    /// column name or index with the upper end of X error bars
+    xhigh: Option<String>,
+
+    #[arg(long, conflicts_with_all = ["ylow", "yhigh"])]
+    /// column name or index with the Y error, error bars or a band around lines
+    yerr: Option<String>,
+
+    #[arg(long)]
+    /// column name or index with the lower end of Y error bars or band
+    ylow: Option<String>,
+
+    #[arg(long)]
+    /// column name or index with the upper end of Y error bars or band
+    yhigh: Option<String>,
+
+    #[arg(long)]
+    /// curve fitted through the points, per color facet category: linear, poly:N (degree N), loess or exp
+    fit: Option<Fit>,
+
+    #[arg(long, requires = "fit")]
+    /// print the fitted formulas and R² to stdout
+    fit_print: bool,
+
+    #[arg(long, requires = "fit")]
+    /// write the fitted formulas and R² onto the chart
+    fit_annotate: bool,
 
     #[arg(long)]
     /// use SI number formatting for X-axis labels (K, M, G, etc.)
@@ -207,33 +325,62 @@ struct Opt
     #[arg(long)]
     /// use SI number formatting for Y-axis labels (K, M, G, etc.)
     si_format_y: bool,
+
+    #[arg(long)]
+    /// use SI number formatting for the gradient color bar labels (K, M, G, etc.)
+    si_format_gradient: bool,
+
+    #[arg(long, default_value = "upper-right")]
+    /// legend position: upper-left, upper-right, lower-left, lower-right, outside
+    legend: LegendPosition,
+
+    #[arg(long)]
+    /// do not draw a legend for series and color facets, nor a color bar for a gradient
+    no_legend: bool,
 }
 
 fn main() -> Result<(), PlotError>
 {
     let mut opt = Opt::parse();
 
-    let mut input: Box<dyn std::io::Read + 'static> = if let Some(path) = &opt.input
-    {
-        Box::new(std::fs::File::open(path)?)
-    }
-    else
+    let delimiter = opt
+        .delimiter
+        .as_deref()
+        .map(input::parse_delimiter)
+        .transpose()?;
+    let df = match opt.input.len()
     {
-        opt.input = Some(std::path::Path::new("STDIN").to_path_buf());
-        Box::new(std::io::stdin())
+        0 | 1 => input::read_columns(
+            opt.input.first().map(|path| path.as_path()),
+            opt.format,
+            delimiter,
+            opt.Header,
+            opt.skip,
+            Some(&column_references(&opt)),
+        )?,
+        _ => overlay_files(&mut opt, delimiter)?,
     };
+    let mut df = expr::filter_rows(df, &opt.filter)?;
+
+    // computed columns restart for each overlaid file
+    let group = (opt.input.len() > 1).then(|| opt.color.clone()).flatten();
+    opt.x = expr::derive_column(&mut df, &opt.x, "X", group.as_deref())?;
+    opt.y = expr::derive_list(&mut df, &opt.y, "Y", group.as_deref())?;
+    opt.color = opt
+        .color
+        .as_deref()
+        .map(|color| expr::derive_column(&mut df, color, "Color", None))
+        .transpose()?;
+
+    if opt.input.is_empty()
+    {
+        opt.input = vec![std::path::Path::new("STDIN").to_path_buf()];
+    }
 
     if opt.outfile.is_none()
     {
-        let mut outname = PathBuf::new();
-        outname.set_file_name(format!(
-            "{}{}",
-            opt.input
-                .as_ref()
-                .ok_or_else(|| PlotError::InvalidData("Input path missing".to_string()))?
-                .file_name()
-                .ok_or_else(|| PlotError::InvalidData("Invalid input filename".to_string()))?
-                .to_string_lossy(),
+        opt.outfile = Some(input::combined_outfile(
+            &opt.input,
             if opt.svg
             {
                 ".plotxy.svg"
@@ -241,484 +388,211 @@ fn main() -> Result<(), PlotError>
             else
             {
                 ".plotxy.png"
-            }
-        ));
-        opt.outfile = Some(outname)
-    }
-
-    // accept escaped delimiters
-    // could be expanded to aliases e.g. "TAB"
-    let delimiter = match opt.delimiter.as_str()
-    {
-        r"\t" => b'\t', // structopt needs r"" to show default as escaped, also for sepcifiying as escaped in CLI
-        _ => *opt
-            .delimiter
-            .as_bytes()
-            .first()
-            .expect("Not a valid delimiter"),
-    };
-
-    // XXX stdin is really hard to use for CsvReader, so slurp the file
-    let mut buf = Vec::new();
-    input.read_to_end(&mut buf).expect("Error reading input");
-
-    let csv_parse_options = CsvParseOptions::default()
-        .with_separator(delimiter)
-        .with_try_parse_dates(false)
-        .with_missing_is_null(true)
-        .with_truncate_ragged_lines(true);
-
-    let csv_read_options = CsvReadOptions::default()
-        .with_parse_options(csv_parse_options)
-        .with_ignore_errors(true)
-        .with_skip_rows(opt.skip)
-        .with_has_header(opt.Header);
-
-    let df = csv_read_options
-        .into_reader_with_file_handle(Cursor::new(buf))
-        .finish()?;
-
-    plot_xy(&opt, df)
-}
-
-fn next_potence(x: f64) -> f64
-{
-    10f64.powf(((x.log10() * 10f64).ceil()) / 10.0)
-}
-
-fn format_si_number(value: f64) -> String
-{
-    let abs_value = value.abs();
-
-    if abs_value >= 1e12
-    {
-        format!("{:.2}T", value / 1e12)
+            },
+        )?);
     }
-    else if abs_value >= 1e9
-    {
-        format!("{:.2}G", value / 1e9)
-    }
-    else if abs_value >= 1e6
-    {
-        format!("{:.2}M", value / 1e6)
-    }
-    else if abs_value >= 1e3
-    {
-        format!("{:.2}K", value / 1e3)
-    }
-    else if abs_value >= 1.0
-    {
-        format!("{:.2}", value)
-    }
-    else if abs_value >= 1e-3
-    {
-        format!("{:.2}m", value * 1e3)
-    }
-    else if abs_value >= 1e-6
-    {
-        format!("{:.2}μ", value * 1e6)
-    }
-    else if abs_value >= 1e-9
-    {
-        format!("{:.2}n", value * 1e9)
-    }
-    else if abs_value >= 1e-12
-    {
-        format!("{:.2}p", value * 1e12)
-    }
-    else if abs_value == 0.0
-    {
-        "0".to_string()
-    }
-    else
-    {
-        format!("{:.2e}", value)
-    }
-}
 
-fn plot_xy(opt: &Opt, df: DataFrame) -> Result<(), PlotError>
-{
     let plot_filename = opt
         .outfile
         .as_ref()
-        .ok_or_else(|| PlotError::InvalidData("Output file path missing".to_string()))?
-        .to_str()
-        .ok_or_else(|| PlotError::InvalidData("Invalid output file path".to_string()))?
-        .to_string();
-
-    println!("{}", plot_filename);
-
-    let number_of_panels = 1;
+        .ok_or_else(|| PlotError::InvalidData("Output file path missing".to_string()))?;
+    println!("{}", plot_filename.display());
 
-    if opt.svg
+    let spec = plot_spec(&opt, &df, plot_filename.to_string_lossy().to_string())?;
+    let format = if opt.svg
     {
-        plot_on_backend(
-            opt,
-            df,
-            SVGBackend::new(&plot_filename, (opt.width, number_of_panels * opt.height)),
-        )?;
+        OutputFormat::Svg
     }
     else
     {
-        plot_on_backend(
-            opt,
-            df,
-            BitMapBackend::new(&plot_filename, (opt.width, number_of_panels * opt.height)),
-        )?;
+        OutputFormat::Bitmap
+    };
+    let report = plotxy::plot_to_file(&spec, &df, plot_filename, format)?;
+    for warning in report.warnings
+    {
+        eprintln!("Warning: {}", warning);
     }
     Ok(())
 }
 
-fn plot_on_backend<'a, B>(opt: &Opt, df: DataFrame, backend: B) -> Result<(), PlotError>
-where
-    B: DrawingBackend,
+/// Reads and stacks several files, the file names become the color facet.
+///
+/// Row numbers as X restart for each file.
+fn overlay_files(opt: &mut Opt, delimiter: Option<Delimiter>) -> Result<DataFrame, PlotError>
 {
-    let plot_filename = opt
-        .outfile
-        .as_ref()
-        .ok_or_else(|| PlotError::InvalidData("Output file path missing".to_string()))?
-        .to_str()
-        .ok_or_else(|| PlotError::InvalidData("Invalid output file path".to_string()))?
-        .to_string();
-
-    let root = Box::new(backend.into_drawing_area());
-    root.fill(&WHITE).expect("root.fill failed");
-    root.titled(
-        opt.title.as_ref().unwrap_or(&plot_filename),
-        (opt.title_font.as_str(), opt.title_font_size),
-    )
-    .expect("root.titled failed");
-
-    let number_of_panels = 1;
-    let panels = root.split_evenly((number_of_panels as usize, 1));
-    let panel = &panels[0];
-    let mut chart = ChartBuilder::on(&panel);
-    let xdesc_area = opt.xdesc_area;
-    let ydesc_area = opt.ydesc_area;
-    chart
-        .x_label_area_size(xdesc_area)
-        .y_label_area_size(ydesc_area)
-        .margin(26u32);
-
-    let idx: Series = (0..df.height() as i64).collect();
-    let x = if opt.x == 0
+    if opt.color.is_some()
     {
-        &idx
+        return Err(PlotError::InvalidData(
+            "Several input files are colored by file, --color cannot be used".to_string(),
+        ));
     }
-    else
-    {
-        df.get_columns()
-            .get(opt.x - 1)
-            .ok_or_else(|| PlotError::InvalidColumn(format!("X column {} not found", opt.x)))?
-            .as_series()
-            .ok_or_else(|| PlotError::InvalidColumn("X column conversion failed".to_string()))?
-    };
-    let y = df
-        .get_columns()
-        .get(opt.y - 1)
-        .ok_or_else(|| PlotError::InvalidColumn(format!("Y column {} not found", opt.y)))?
-        .as_series()
-        .ok_or_else(|| PlotError::InvalidColumn("Y column conversion failed".to_string()))?;
-    let x_max: f64 = x
-        .max()?
-        .ok_or_else(|| PlotError::InvalidData("No data in X column".to_string()))?;
-    let y_max: f64 = y
-        .max()?
-        .ok_or_else(|| PlotError::InvalidData("No data in Y column".to_string()))?;
-    let _y_min: f64 = y
-        .min()?
-        .ok_or_else(|| PlotError::InvalidData("No data in Y column".to_string()))?;
-
-    let xf64 = x.cast(&DataType::Float64)?;
-    let yf64 = y.cast(&DataType::Float64)?;
-    let xyc = make_xyc(&xf64, &yf64, &df, &opt)?;
-
-    match opt.shape.as_str()
+    let references = column_references(opt);
+    let mut tables = Vec::with_capacity(opt.input.len());
+    for path in &opt.input
     {
-        "column" =>
-        {
-            let shapes = xyc.map(|((x, y), c)| match (x, y)
-            {
-                (Some(xx), Some(yy)) => Rectangle::new([(xx - 0.4f64, yy), (xx + 0.4f64, 0f64)], c),
-                _ =>
-                {
-                    println!("NA value as 0 0");
-                    Rectangle::new([(0.0, 0.0), (0.0, 0.0)], c)
-                }
-            });
-            plot_shapes(&mut chart, shapes, &opt, x_max, y_max)?;
-            Ok(())
-        }
-        _ =>
-        {
-            let shapes = xyc.map(|((x, y), c)| match (x, y)
-            {
-                (Some(xx), Some(yy)) => Circle::new((xx, yy), opt.point_size, c),
-                _ =>
-                {
-                    println!("NA value as 0 0");
-                    Circle::new((0.0, 0.0), opt.point_size, c)
-                }
-            });
-            plot_shapes(&mut chart, shapes, &opt, x_max, y_max)?;
-            Ok(())
-        }
+        let df = input::read_columns(
+            Some(path),
+            opt.format,
+            delimiter.clone(),
+            opt.Header,
+            opt.skip,
+            Some(&references),
+        )?;
+        tables.push((path.display().to_string(), df));
     }
-}
-
-/// Returns an iterator over x/y points and the color based on facet/gradient
-fn make_xyc<'a, 'b>(
-    x: &'a Series,
-    y: &'b Series,
-    df: &DataFrame,
-    opt: &Opt,
-) -> Result<
-    Zip<
-        Zip<
-            Box<dyn PolarsIterator<Item = Option<f64>> + 'a>,
-            Box<dyn PolarsIterator<Item = Option<f64>> + 'b>,
-        >,
-        std::vec::IntoIter<ShapeStyle>,
-    >,
-    PlotError,
->
-{
-    let plot_color = hex::decode(&opt.plot_color)?;
-    let plot_plotters_color = RGBColor(plot_color[0], plot_color[1], plot_color[2]);
-    let xy = x
-        .f64()
-        .map_err(|_| PlotError::InvalidData("X column is not numeric".to_string()))?
-        .into_iter()
-        .zip(
-            y.f64()
-                .map_err(|_| PlotError::InvalidData("Y column is not numeric".to_string()))?
-                .into_iter(),
-        );
-
-    let color_iterator = if let Some(color_facet_index) = opt.color
+    let row_x = plotxy::column_index(&tables[0].1, &opt.x, "X").is_ok_and(|index| index == 0);
+    if row_x
     {
-        let color_series = df
-            .get_columns()
-            .get(color_facet_index - 1)
-            .ok_or_else(|| {
-                PlotError::InvalidColumn(format!("Color column {} not found", color_facet_index))
-            })?
-            .as_series()
-            .ok_or_else(|| {
-                PlotError::InvalidColumn("Color column conversion failed".to_string())
-            })?;
-
-        // Handle numeric and string data differently for better categorical mapping
-        if color_series.dtype().is_primitive_numeric()
-        {
-            // For numeric data, use values directly
-            let numeric_series = color_series.cast(&DataType::Float64)?;
-            numeric_series
-                .f64()
-                .map_err(|_| PlotError::InvalidData("Color column is not numeric".to_string()))?
-                .into_iter()
-                .map(|c| ShapeStyle::from(Palette99::pick(c.unwrap_or(0.0) as usize)).filled())
-                .collect()
-        }
-        else
+        for (_, df) in tables.iter_mut()
         {
-            // For string data, create a mapping from unique strings to color indices
-            let string_series = color_series.cast(&DataType::String)?;
-            let string_values = string_series
-                .str()
-                .map_err(|_| PlotError::InvalidData("Color column is not string".to_string()))?;
-
-            // Get unique values to create a consistent color mapping
-            let unique_values: std::collections::HashSet<_> =
-                string_values.into_iter().filter_map(|s| s).collect();
-
-            let value_to_index: std::collections::HashMap<String, usize> = unique_values
-                .into_iter()
-                .enumerate()
-                .map(|(i, s)| (s.to_string(), i))
-                .collect();
-
-            // Map each string value to its color index
-            string_values
-                .into_iter()
-                .map(|s| {
-                    let color_index = s
-                        .and_then(|str_val| value_to_index.get(str_val))
-                        .copied()
-                        .unwrap_or(0);
-                    ShapeStyle::from(Palette99::pick(color_index)).filled()
-                })
-                .collect()
+            let rows: Series = (0..df.height() as i64).collect();
+            df.with_column(rows.with_name("Row".into()))?;
         }
     }
-    else if let Some(color_gradient_index) = opt.gradient
+
+    let (df, file_column) = input::stack_labelled(tables, "file")?;
+    if row_x
     {
-        get_gradient_color_iter(
-            &opt,
-            df.get_columns()
-                .get(color_gradient_index - 1)
-                .ok_or_else(|| {
-                    PlotError::InvalidColumn(format!(
-                        "Gradient column {} not found",
-                        color_gradient_index
-                    ))
-                })?
-                .as_series()
-                .ok_or_else(|| {
-                    PlotError::InvalidColumn("Gradient column conversion failed".to_string())
-                })?,
-        )?
+        opt.x = "Row".to_string();
+        opt.xdesc.get_or_insert_with(|| "Row".to_string());
     }
-    else
-    {
-        (0..xy.len())
-            .into_iter()
-            .map(|_c| ShapeStyle::from(plot_plotters_color.mix(opt.alpha)).filled())
-            .collect()
-    };
-    Ok(xy.zip(color_iterator))
+    opt.color = Some(file_column);
+    Ok(df)
 }
 
-// Compact macro for mesh configuration - much shorter than the original 16 duplicated blocks
-macro_rules! configure_and_draw_mesh {
-    ($grid:expr, $opt:expr, $shapes:expr) => {{
-        let mesh_result = match ($opt.si_format_x, $opt.si_format_y)
-        {
-            (true, true) => $grid
-                .configure_mesh()
-                .disable_x_mesh()
-                .bold_line_style(WHITE.mix(0.3))
-                .y_desc(&$opt.ydesc)
-                .x_desc(&$opt.xdesc)
-                .label_style(($opt.label_font.as_str(), $opt.label_font_size))
-                .axis_desc_style(($opt.axis_desc_font.as_str(), $opt.axis_desc_font_size))
-                .x_label_formatter(&|x| format_si_number(*x))
-                .y_label_formatter(&|y| format_si_number(*y))
-                .draw(),
-            (true, false) => $grid
-                .configure_mesh()
-                .disable_x_mesh()
-                .bold_line_style(WHITE.mix(0.3))
-                .y_desc(&$opt.ydesc)
-                .x_desc(&$opt.xdesc)
-                .label_style(($opt.label_font.as_str(), $opt.label_font_size))
-                .axis_desc_style(($opt.axis_desc_font.as_str(), $opt.axis_desc_font_size))
-                .x_label_formatter(&|x| format_si_number(*x))
-                .draw(),
-            (false, true) => $grid
-                .configure_mesh()
-                .disable_x_mesh()
-                .bold_line_style(WHITE.mix(0.3))
-                .y_desc(&$opt.ydesc)
-                .x_desc(&$opt.xdesc)
-                .label_style(($opt.label_font.as_str(), $opt.label_font_size))
-                .axis_desc_style(($opt.axis_desc_font.as_str(), $opt.axis_desc_font_size))
-                .y_label_formatter(&|y| format_si_number(*y))
-                .draw(),
-            (false, false) => $grid
-                .configure_mesh()
-                .disable_x_mesh()
-                .bold_line_style(WHITE.mix(0.3))
-                .y_desc(&$opt.ydesc)
-                .x_desc(&$opt.xdesc)
-                .label_style(($opt.label_font.as_str(), $opt.label_font_size))
-                .axis_desc_style(($opt.axis_desc_font.as_str(), $opt.axis_desc_font_size))
-                .draw(),
-        };
-        mesh_result.map_err(|e| PlotError::InvalidData(format!("Draw error: {}", e)))?;
-        $grid
-            .draw_series($shapes)
-            .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
-    }};
-}
-
-fn plot_shapes<'a, 'b, DB, T>(
-    chart: &mut ChartBuilder<'a, 'b, DB>,
-    shapes: T,
-    opt: &Opt,
-    x_max: f64,
-    y_max: f64,
-) -> Result<(), PlotError>
-where
-    DB: DrawingBackend,
-    T: IntoIterator,
-    T::Item: Drawable<DB>,
-    for<'d> &'d <T as IntoIterator>::Item: PointCollection<'d, (f64, f64)>,
+/// Every option that names columns, only those are read from the input
+fn column_references(opt: &Opt) -> Vec<&str>
 {
-    let x_dim_min = opt.x_dim_min;
-    let y_dim_min = opt.y_dim_min;
-    let x_dim_max = opt.x_dim_max.unwrap_or(next_potence(x_max as f64));
-    let y_dim_max = opt.y_dim_max.unwrap_or(next_potence(y_max as f64));
-
-    match (opt.logx, opt.logy)
-    {
-        (true, true) =>
-        {
-            let mut grid = chart
-                .build_cartesian_2d(
-                    (x_dim_min..x_dim_max).log_scale(),
-                    (y_dim_min..y_dim_max).log_scale(),
-                )
-                .map_err(|e| PlotError::InvalidData(format!("Grid creation error: {}", e)))?;
-            configure_and_draw_mesh!(grid, opt, shapes);
-        }
-        (true, false) =>
-        {
-            let mut grid = chart
-                .build_cartesian_2d((x_dim_min..x_dim_max).log_scale(), y_dim_min..y_dim_max)
-                .map_err(|e| PlotError::InvalidData(format!("Grid creation error: {}", e)))?;
-            configure_and_draw_mesh!(grid, opt, shapes);
-        }
-        (false, true) =>
-        {
-            let mut grid = chart
-                .build_cartesian_2d(x_dim_min..x_dim_max, (y_dim_min..y_dim_max).log_scale())
-                .map_err(|e| PlotError::InvalidData(format!("Grid creation error: {}", e)))?;
-            configure_and_draw_mesh!(grid, opt, shapes);
-        }
-        (false, false) =>
-        {
-            let mut grid = chart
-                .build_cartesian_2d(x_dim_min..x_dim_max, y_dim_min..y_dim_max)
-                .map_err(|e| PlotError::InvalidData(format!("Grid creation error: {}", e)))?;
-            configure_and_draw_mesh!(grid, opt, shapes);
-        }
-    }
-    Ok(())
+    let mut references = vec![opt.x.as_str(), opt.y.as_str()];
+    references.extend(
+        [
+            &opt.color,
+            &opt.gradient,
+            &opt.facet,
+            &opt.xerr,
+            &opt.xlow,
+            &opt.xhigh,
+            &opt.yerr,
+            &opt.ylow,
+            &opt.yhigh,
+        ]
+        .into_iter()
+        .filter_map(|reference| reference.as_deref()),
+    );
+    references.extend(opt.filter.iter().map(String::as_str));
+    references
 }
 
-fn get_gradient_color_iter(opt: &Opt, series: &Series) -> Result<Vec<ShapeStyle>, PlotError>
+/// Translates the command line options into a PlotSpec, the title defaults to the output filename
+fn plot_spec(opt: &Opt, df: &DataFrame, plot_filename: String) -> Result<PlotSpec, PlotError>
 {
-    let float_series = series.cast(&DataType::Float32)?;
-    let values = float_series
-        .f32()
-        .map_err(|_| PlotError::InvalidData("Gradient column is not numeric".to_string()))?;
-    let grad = colorgrad::GradientBuilder::new()
-        .html_colors(&["yellow", "red"])
-        .domain(&[
-            values.min().ok_or_else(|| {
-                PlotError::InvalidData("No minimum value in gradient column".to_string())
-            })?,
-            values.max().ok_or_else(|| {
-                PlotError::InvalidData("No maximum value in gradient column".to_string())
-            })?,
-        ])
-        .build::<colorgrad::LinearGradient>()
-        .expect("prebuilt gradient should always work");
-
-    let color_vec = values
-        .into_iter()
-        .map(|c| {
-            ShapeStyle::from(
-                rbgcolor_from_gradient(grad.at(c.unwrap_or(0.0) as f32).to_rgba8(), opt.alpha)
-                    .filled(),
-            )
-        })
-        .collect();
-    Ok(color_vec)
+    Ok(PlotSpec {
+        x: plotxy::column_index(df, &opt.x, "X")?,
+        y: plotxy::column_list(df, &opt.y, "Y")?,
+        color: opt
+            .color
+            .as_deref()
+            .map(|color| plotxy::column_index(df, color, "Color"))
+            .transpose()?,
+        gradient: opt
+            .gradient
+            .as_deref()
+            .map(|gradient| plotxy::column_index(df, gradient, "Gradient"))
+            .transpose()?,
+        facet: opt
+            .facet
+            .as_deref()
+            .map(|facet| plotxy::column_index(df, facet, "Facet"))
+            .transpose()?,
+        facet_rows: opt.facet_rows,
+        facet_columns: opt.facet_columns,
+        facet_scales: opt.facet_scales,
+        palette: opt.palette.clone(),
+        color_order: opt.color_order,
+        color_map: opt
+            .color_map
+            .as_deref()
+            .map(color::parse_color_map)
+            .transpose()?
+            .unwrap_or_default(),
+        gradient_scheme: opt.gradient_scheme.clone(),
+        gradient_min: opt.gradient_min,
+        gradient_max: opt.gradient_max,
+        gradient_log: opt.gradient_log,
+        alpha: opt.alpha,
+        plot_color: color::parse_hex_color(&opt.plot_color)?,
+        logx: opt.logx,
+        logy: opt.logy,
+        x_time: opt.x_time.clone(),
+        time_format: opt.time_format.clone(),
+        x_categories: opt.x_categories,
+        rotate_x_labels: opt.rotate_x_labels,
+        x_dim_min: opt.x_dim_min,
+        x_dim_max: opt.x_dim_max,
+        y_dim_min: opt.y_dim_min,
+        y_dim_max: opt.y_dim_max,
+        x_range: opt.x_range,
+        y_range: opt.y_range,
+        title: Some(opt.title.clone().unwrap_or(plot_filename)),
+        width: opt.width,
+        height: opt.height,
+        xdesc: opt.xdesc.clone(),
+        ydesc: opt.ydesc.clone(),
+        xdesc_area: opt.xdesc_area,
+        ydesc_area: opt.ydesc_area,
+        label_font: opt.label_font.clone(),
+        label_font_size: opt.label_font_size,
+        axis_desc_font: opt.axis_desc_font.clone(),
+        axis_desc_font_size: opt.axis_desc_font_size,
+        title_font: opt.title_font.clone(),
+        title_font_size: opt.title_font_size,
+        point_size: opt.point_size,
+        shape: opt.shape,
+        cells: opt.cells,
+        cell_width: opt.cell_width,
+        cell_height: opt.cell_height,
+        hist: opt.hist.then(|| Histogram {
+            bins: opt.bin_width.map_or(opt.bins, Bins::Width),
+            log: opt.log_bins,
+            density: opt.density,
+            stack: opt.stack,
+        }),
+        aggregate: opt.aggregate,
+        error_bars: opt.error_bars,
+        x_error: error_columns(df, &opt.xerr, &opt.xlow, &opt.xhigh, "X")?,
+        y_error: error_columns(df, &opt.yerr, &opt.ylow, &opt.yhigh, "Y")?,
+        fit: opt.fit,
+        fit_print: opt.fit_print,
+        fit_annotate: opt.fit_annotate,
+        sort_x: opt.sort_x,
+        line_width: opt.line_width,
+        si_format_x: opt.si_format_x,
+        si_format_y: opt.si_format_y,
+        si_format_gradient: opt.si_format_gradient,
+        legend: !opt.no_legend,
+        legend_position: opt.legend,
+    })
 }
 
-fn rbgcolor_from_gradient(g: [u8; 4], alpha: f64) -> RGBAColor
+/// Error columns of one axis, symmetric or given as lower and upper ends
+fn error_columns(
+    df: &DataFrame,
+    error: &Option<String>,
+    low: &Option<String>,
+    high: &Option<String>,
+    axis: &str,
+) -> Result<Option<ErrorColumns>, PlotError>
 {
-    RGBAColor(g[0], g[1], g[2], alpha)
+    let index = |reference: &Option<String>, role: &str| {
+        reference
+            .as_deref()
+            .map(|reference| plotxy::column_index(df, reference, &format!("{} {}", axis, role)))
+            .transpose()
+    };
+    Ok(match (index(error, "error")?, index(low, "low")?, index(high, "high")?)
+    {
+        (Some(error), _, _) => Some(ErrorColumns::Symmetric(error)),
+        (None, None, None) => None,
+        (None, low, high) => Some(ErrorColumns::Range { low, high }),
+    })
 }
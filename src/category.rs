@@ -0,0 +1,180 @@
+//! Categorical X axes.
+//!
+//! Each distinct X value gets a slot, slots sit on whole numbers starting at 0 so they
+//! plot like any other number and a column is centered on its slot like plotters'
+//! segmented coordinates do.
+
+use crate::PlotError;
+
+use plotters::coord::ranged1d::{KeyPointHint, NoDefaultFormatting, Ranged, ValueFormatter};
+use plotters::coord::types::RangedCoordf64;
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Order of the slots on a categorical X axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum XOrder
+{
+    /// order of first appearance in the input
+    #[default]
+    Input,
+    Alphabetical,
+    /// largest Y value of each category first
+    Y,
+}
+
+impl FromStr for XOrder
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "input" => Ok(XOrder::Input),
+            "alphabetical" => Ok(XOrder::Alphabetical),
+            "y" => Ok(XOrder::Y),
+            _ => Err(format!("unknown category order {}, expected input, alphabetical or y", s)),
+        }
+    }
+}
+
+/// Slot number for each row of `x` and the category name of each slot.
+///
+/// `y` is only used for ordering by Y, missing X values form the category NA.
+pub fn slots(x: &Series, y: &Series, order: XOrder) -> Result<(Series, Vec<String>), PlotError>
+{
+    let strings = x.cast(&DataType::String)?;
+    let names: Vec<&str> = strings
+        .str()
+        .map_err(|_| PlotError::InvalidData("X column is not string".to_string()))?
+        .into_iter()
+        .map(|s| s.unwrap_or("NA"))
+        .collect();
+
+    let mut categories: Vec<&str> = Vec::new();
+    let mut largest_y: HashMap<&str, f64> = HashMap::new();
+    let y = y.cast(&DataType::Float64)?;
+    let y = y
+        .f64()
+        .map_err(|_| PlotError::InvalidData("Y column is not numeric".to_string()))?;
+    for (&name, y) in names.iter().zip(y)
+    {
+        let largest = largest_y.entry(name).or_insert_with(|| {
+            categories.push(name);
+            f64::NEG_INFINITY
+        });
+        *largest = largest.max(y.unwrap_or(f64::NEG_INFINITY));
+    }
+    match order
+    {
+        XOrder::Input => (),
+        XOrder::Alphabetical => categories.sort(),
+        XOrder::Y => categories.sort_by(|a, b| largest_y[b].total_cmp(&largest_y[a])),
+    }
+
+    let categories: Vec<String> = categories.into_iter().map(str::to_string).collect();
+    Ok((slots_in(x, &categories)?, categories))
+}
+
+/// Slot number for each row of `x` on an axis with the given categories, e.g. those of all
+/// panels. Values that are not among them are left out.
+pub fn slots_in(x: &Series, categories: &[String]) -> Result<Series, PlotError>
+{
+    let strings = x.cast(&DataType::String)?;
+    let slot: HashMap<&str, usize> = categories
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.as_str(), i))
+        .collect();
+    let x_slots: Float64Chunked = strings
+        .str()
+        .map_err(|_| PlotError::InvalidData("X column is not string".to_string()))?
+        .into_iter()
+        .map(|name| slot.get(name.unwrap_or("NA")).map(|&slot| slot as f64))
+        .collect();
+    Ok(x_slots.with_name(x.name().clone()).into_series())
+}
+
+/// Axis with one labelled slot per category
+pub struct CategoryAxis
+{
+    slots: RangedCoordf64,
+    names: Vec<String>,
+}
+
+impl CategoryAxis
+{
+    pub fn new(names: &[String]) -> CategoryAxis
+    {
+        CategoryAxis {
+            slots: (-0.5..names.len() as f64 - 0.5).into(),
+            names: names.to_vec(),
+        }
+    }
+}
+
+impl Ranged for CategoryAxis
+{
+    type FormatOption = NoDefaultFormatting;
+    type ValueType = f64;
+
+    fn map(&self, value: &f64, limit: (i32, i32)) -> i32
+    {
+        self.slots.map(value, limit)
+    }
+
+    /// Every slot gets a label, long names can be rotated instead of skipped
+    fn key_points<Hint: KeyPointHint>(&self, hint: Hint) -> Vec<f64>
+    {
+        if hint.weight().allow_light_points()
+        {
+            return Vec::new();
+        }
+        (0..self.names.len()).map(|slot| slot as f64).collect()
+    }
+
+    fn range(&self) -> Range<f64>
+    {
+        self.slots.range()
+    }
+}
+
+impl ValueFormatter<f64> for CategoryAxis
+{
+    fn format_ext(&self, value: &f64) -> String
+    {
+        let slot = value.round();
+        if (value - slot).abs() < 1e-6 && slot >= 0.0
+        {
+            self.names.get(slot as usize).cloned().unwrap_or_default()
+        }
+        else
+        {
+            String::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn slots_of_all_panels()
+    {
+        let x = Series::new("host".into(), ["b", "c", "a", "c"]);
+        let y = Series::new("v".into(), [1.0, 5.0, 3.0, 2.0]);
+        let (_, names) = slots(&x, &y, XOrder::Y).unwrap();
+        assert_eq!(names, ["c", "a", "b"]);
+
+        // a panel with a subset of the rows keeps the slots of all rows
+        let panel = Series::new("host".into(), ["b", "b", "d"]);
+        let panel_slots = slots_in(&panel, &names).unwrap();
+        let panel_slots: Vec<Option<f64>> = panel_slots.f64().unwrap().into_iter().collect();
+        assert_eq!(panel_slots, [Some(2.0), Some(2.0), None]);
+    }
+}
@@ -0,0 +1,648 @@
+use crate::PlotError;
+
+use colorgrad::Gradient;
+use plotters::prelude::*;
+use polars::prelude::*;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Parses a hex color like `1E88E5`
+pub fn parse_hex_color(color: &str) -> Result<RGBColor, PlotError>
+{
+    let rgb = hex::decode(color)?;
+    if rgb.len() < 3
+    {
+        return Err(PlotError::InvalidData(format!("Color {} needs three bytes", color)));
+    }
+    Ok(RGBColor(rgb[0], rgb[1], rgb[2]))
+}
+
+/// Palette names accepted by `Palette::from_str`, besides a list of hex colors
+pub const PALETTE_NAMES: &[&str] = &[
+    "palette99",
+    "tableau10",
+    "set1",
+    "set2",
+    "set3",
+    "dark2",
+    "paired",
+    "pastel1",
+    "accent",
+];
+
+const TABLEAU10: &[u32] = &[
+    0x4E79A7, 0xF28E2B, 0xE15759, 0x76B7B2, 0x59A14F, 0xEDC948, 0xB07AA1, 0xFF9DA7, 0x9C755F,
+    0xBAB0AC,
+];
+// ColorBrewer qualitative sets
+const SET1: &[u32] = &[
+    0xE41A1C, 0x377EB8, 0x4DAF4A, 0x984EA3, 0xFF7F00, 0xFFFF33, 0xA65628, 0xF781BF, 0x999999,
+];
+const SET2: &[u32] = &[
+    0x66C2A5, 0xFC8D62, 0x8DA0CB, 0xE78AC3, 0xA6D854, 0xFFD92F, 0xE5C494, 0xB3B3B3,
+];
+const SET3: &[u32] = &[
+    0x8DD3C7, 0xFFFFB3, 0xBEBADA, 0xFB8072, 0x80B1D3, 0xFDB462, 0xB3DE69, 0xFCCDE5, 0xD9D9D9,
+    0xBC80BD, 0xCCEBC5, 0xFFED6F,
+];
+const DARK2: &[u32] = &[
+    0x1B9E77, 0xD95F02, 0x7570B3, 0xE7298A, 0x66A61E, 0xE6AB02, 0xA6761D, 0x666666,
+];
+const PAIRED: &[u32] = &[
+    0xA6CEE3, 0x1F78B4, 0xB2DF8A, 0x33A02C, 0xFB9A99, 0xE31A1C, 0xFDBF6F, 0xFF7F00, 0xCAB2D6,
+    0x6A3D9A, 0xFFFF99, 0xB15928,
+];
+const PASTEL1: &[u32] = &[
+    0xFBB4AE, 0xB3CDE3, 0xCCEBC5, 0xDECBE4, 0xFED9A6, 0xFFFFCC, 0xE5D8BD, 0xFDDAEC, 0xF2F2F2,
+];
+const ACCENT: &[u32] = &[
+    0x7FC97F, 0xBEAED4, 0xFDC086, 0xFFFF99, 0x386CB0, 0xF0027F, 0xBF5B17, 0x666666,
+];
+
+/// Colors for categories and series, picked by index and repeated when exhausted
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Palette
+{
+    #[default]
+    Palette99,
+    Tableau10,
+    Set1,
+    Set2,
+    Set3,
+    Dark2,
+    Paired,
+    Pastel1,
+    Accent,
+    /// these colors, the default palette if the list is empty
+    Custom(Vec<RGBColor>),
+}
+
+impl Palette
+{
+    /// Color for the `index`th category or series
+    pub fn pick(&self, index: usize) -> RGBColor
+    {
+        let table = match self
+        {
+            Palette::Custom(colors) if !colors.is_empty() => return colors[index % colors.len()],
+            Palette::Palette99 | Palette::Custom(_) =>
+            {
+                let (r, g, b) = <Palette99 as plotters::style::Palette>::pick(index).rgb();
+                return RGBColor(r, g, b);
+            }
+            Palette::Tableau10 => TABLEAU10,
+            Palette::Set1 => SET1,
+            Palette::Set2 => SET2,
+            Palette::Set3 => SET3,
+            Palette::Dark2 => DARK2,
+            Palette::Paired => PAIRED,
+            Palette::Pastel1 => PASTEL1,
+            Palette::Accent => ACCENT,
+        };
+        let rgb = table[index % table.len()];
+        RGBColor((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+}
+
+impl FromStr for Palette
+{
+    type Err = String;
+
+    /// A palette name or a comma separated list of hex colors
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s.to_lowercase().replace(['-', '_'], "").as_str()
+        {
+            "palette99" => Ok(Palette::Palette99),
+            "tableau10" => Ok(Palette::Tableau10),
+            "set1" => Ok(Palette::Set1),
+            "set2" => Ok(Palette::Set2),
+            "set3" => Ok(Palette::Set3),
+            "dark2" => Ok(Palette::Dark2),
+            "paired" => Ok(Palette::Paired),
+            "pastel1" => Ok(Palette::Pastel1),
+            "accent" => Ok(Palette::Accent),
+            _ => s
+                .split(',')
+                .map(|c| parse_hex_color(c.trim().trim_start_matches('#')))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Palette::Custom)
+                .map_err(|_| {
+                    format!(
+                        "unknown palette {}, expected a hex color list like 1E88E5,D81B60 or one of {}",
+                        s,
+                        PALETTE_NAMES.join(", ")
+                    )
+                }),
+        }
+    }
+}
+
+/// Order of color facet categories, which decides their colors and legend order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CategoryOrder
+{
+    /// numerically for numeric columns, alphabetically otherwise
+    #[default]
+    Sorted,
+    /// order of first appearance in the input
+    Appearance,
+}
+
+impl FromStr for CategoryOrder
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "sorted" => Ok(CategoryOrder::Sorted),
+            "appearance" => Ok(CategoryOrder::Appearance),
+            _ => Err(format!("unknown category order {}, expected sorted or appearance", s)),
+        }
+    }
+}
+
+/// Parses fixed category colors like `ok=2E7D32,fail=C62828`.
+///
+/// An existing file is read instead, with one `category=color` or tab separated pair per line.
+pub fn parse_color_map(map: &str) -> Result<Vec<(String, RGBColor)>, PlotError>
+{
+    let path = std::path::Path::new(map);
+    let (content, separator) = if path.is_file()
+    {
+        (std::fs::read_to_string(path)?, '\n')
+    }
+    else
+    {
+        (map.to_string(), ',')
+    };
+    content
+        .split(separator)
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty() && !entry.starts_with('#'))
+        .map(|entry| {
+            let (name, color) = entry
+                .rsplit_once('=')
+                .or_else(|| entry.rsplit_once('\t'))
+                .ok_or_else(|| {
+                    PlotError::InvalidData(format!(
+                        "Color map entry {} is not of the form category=color",
+                        entry
+                    ))
+                })?;
+            Ok((name.trim().to_string(), parse_hex_color(color.trim().trim_start_matches('#'))?))
+        })
+        .collect()
+}
+
+/// Category per row of a color facet column, rows with the same index form a group
+pub struct Facet
+{
+    /// index into `categories` for each row
+    pub indices: Vec<usize>,
+    /// legend entries, category name and its color
+    pub categories: Vec<(String, RGBColor)>,
+}
+
+impl Facet
+{
+    /// Categories pick the palette color by their position in `order`, numeric ones sorted by
+    /// value. Missing values form the category NA.
+    pub fn new(
+        color_series: &Series,
+        palette: &Palette,
+        order: CategoryOrder,
+        color_map: &[(String, RGBColor)],
+    ) -> Result<Facet, PlotError>
+    {
+        // category name and numeric value per row
+        let rows: Vec<(String, Option<f64>)> = if color_series.dtype().is_primitive_numeric()
+        {
+            let numeric_series = color_series.cast(&DataType::Float64)?;
+            numeric_series
+                .f64()
+                .map_err(|_| PlotError::InvalidData("Color column is not numeric".to_string()))?
+                .into_iter()
+                .map(|c| match c
+                {
+                    Some(c) => (c.to_string(), Some(c)),
+                    None => ("NA".to_string(), None),
+                })
+                .collect()
+        }
+        else
+        {
+            let string_series = color_series.cast(&DataType::String)?;
+            string_series
+                .str()
+                .map_err(|_| PlotError::InvalidData("Color column is not string".to_string()))?
+                .into_iter()
+                .map(|s| (s.unwrap_or("NA").to_string(), None))
+                .collect()
+        };
+
+        // unique categories in order of appearance
+        let mut unique: Vec<(String, Option<f64>)> = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        for (name, value) in &rows
+        {
+            if seen.insert(name.as_str())
+            {
+                unique.push((name.clone(), *value));
+            }
+        }
+        if order == CategoryOrder::Sorted
+        {
+            unique.sort_by(|(a_name, a), (b_name, b)| match (a, b)
+            {
+                (Some(a), Some(b)) => a.total_cmp(b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => a_name.cmp(b_name),
+            });
+        }
+
+        let position: std::collections::HashMap<&str, usize> = unique
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (name.as_str(), i))
+            .collect();
+        let categories = unique
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| {
+                let color = match color_map.iter().find(|(category, _)| category == name)
+                {
+                    Some((_, color)) => *color,
+                    None => palette.pick(i),
+                };
+                (name.clone(), color)
+            })
+            .collect();
+        Ok(Facet {
+            indices: rows
+                .iter()
+                .map(|(name, _)| position[name.as_str()])
+                .collect(),
+            categories,
+        })
+    }
+
+    /// Facet of the rows selected by `mask`, keeping all categories and their colors
+    pub fn filter(&self, mask: &BooleanChunked) -> Facet
+    {
+        Facet {
+            indices: self
+                .indices
+                .iter()
+                .zip(mask)
+                .filter(|(_, selected)| selected.unwrap_or(false))
+                .map(|(&category, _)| category)
+                .collect(),
+            categories: self.categories.clone(),
+        }
+    }
+}
+
+/// Preset names accepted by `GradientScheme::Preset`
+pub const GRADIENT_PRESETS: &[&str] = &[
+    "viridis",
+    "inferno",
+    "magma",
+    "plasma",
+    "turbo",
+    "cividis",
+    "rainbow",
+    "sinebow",
+    "warm",
+    "cool",
+    "cubehelix",
+    "spectral",
+    "rdbu",
+    "rdgy",
+    "rdylbu",
+    "rdylgn",
+    "brbg",
+    "prgn",
+    "piyg",
+    "puor",
+    "blues",
+    "greens",
+    "greys",
+    "oranges",
+    "purples",
+    "reds",
+    "bugn",
+    "bupu",
+    "gnbu",
+    "orrd",
+    "pubu",
+    "pubugn",
+    "purd",
+    "rdpu",
+    "ylgn",
+    "ylgnbu",
+    "ylorbr",
+    "ylorrd",
+];
+
+/// Colors of a gradient
+#[derive(Debug, Clone, PartialEq)]
+pub enum GradientScheme
+{
+    /// one of `GRADIENT_PRESETS`
+    Preset(String),
+    /// CSS color names or hex colors, evenly spaced
+    Colors(Vec<String>),
+    /// GIMP gradient file
+    Ggr(PathBuf),
+}
+
+impl Default for GradientScheme
+{
+    fn default() -> Self
+    {
+        GradientScheme::Colors(vec!["yellow".to_string(), "red".to_string()])
+    }
+}
+
+impl FromStr for GradientScheme
+{
+    type Err = String;
+
+    /// A path ending in .ggr, a comma separated color list or a preset name
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        if s.to_lowercase().ends_with(".ggr")
+        {
+            Ok(GradientScheme::Ggr(PathBuf::from(s)))
+        }
+        else if s.contains(',')
+        {
+            Ok(GradientScheme::Colors(s.split(',').map(|c| c.trim().to_string()).collect()))
+        }
+        else
+        {
+            let name = s.to_lowercase().replace(['-', '_'], "");
+            if GRADIENT_PRESETS.contains(&name.as_str())
+            {
+                Ok(GradientScheme::Preset(name))
+            }
+            else
+            {
+                Err(format!(
+                    "unknown gradient {}, expected a .ggr file, a color list like white,red or one of {}",
+                    s,
+                    GRADIENT_PRESETS.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+impl GradientScheme
+{
+    fn build(&self) -> Result<Box<dyn Gradient>, PlotError>
+    {
+        use colorgrad::preset::*;
+        let gradient = match self
+        {
+            GradientScheme::Preset(name) => match name.as_str()
+            {
+                "viridis" => viridis().boxed(),
+                "inferno" => inferno().boxed(),
+                "magma" => magma().boxed(),
+                "plasma" => plasma().boxed(),
+                "turbo" => turbo().boxed(),
+                "cividis" => cividis().boxed(),
+                "rainbow" => rainbow().boxed(),
+                "sinebow" => sinebow().boxed(),
+                "warm" => warm().boxed(),
+                "cool" => cool().boxed(),
+                "cubehelix" => cubehelix_default().boxed(),
+                "spectral" => spectral().boxed(),
+                "rdbu" => rd_bu().boxed(),
+                "rdgy" => rd_gy().boxed(),
+                "rdylbu" => rd_yl_bu().boxed(),
+                "rdylgn" => rd_yl_gn().boxed(),
+                "brbg" => br_bg().boxed(),
+                "prgn" => pr_gn().boxed(),
+                "piyg" => pi_yg().boxed(),
+                "puor" => pu_or().boxed(),
+                "blues" => blues().boxed(),
+                "greens" => greens().boxed(),
+                "greys" => greys().boxed(),
+                "oranges" => oranges().boxed(),
+                "purples" => purples().boxed(),
+                "reds" => reds().boxed(),
+                "bugn" => bu_gn().boxed(),
+                "bupu" => bu_pu().boxed(),
+                "gnbu" => gn_bu().boxed(),
+                "orrd" => or_rd().boxed(),
+                "pubu" => pu_bu().boxed(),
+                "pubugn" => pu_bu_gn().boxed(),
+                "purd" => pu_rd().boxed(),
+                "rdpu" => rd_pu().boxed(),
+                "ylgn" => yl_gn().boxed(),
+                "ylgnbu" => yl_gn_bu().boxed(),
+                "ylorbr" => yl_or_br().boxed(),
+                "ylorrd" => yl_or_rd().boxed(),
+                _ =>
+                {
+                    return Err(PlotError::InvalidData(format!("Unknown gradient preset {}", name)))
+                }
+            },
+            GradientScheme::Colors(colors) =>
+            {
+                // accept hex colors without #, like --plot-color
+                let colors: Vec<String> = colors
+                    .iter()
+                    .map(|c| {
+                        if c.len() == 6 && c.chars().all(|h| h.is_ascii_hexdigit())
+                        {
+                            format!("#{}", c)
+                        }
+                        else
+                        {
+                            c.clone()
+                        }
+                    })
+                    .collect();
+                colorgrad::GradientBuilder::new()
+                    .html_colors(&colors)
+                    .build::<colorgrad::LinearGradient>()
+                    .map_err(|e| PlotError::InvalidData(format!("Invalid gradient colors: {}", e)))?
+                    .boxed()
+            }
+            GradientScheme::Ggr(path) =>
+            {
+                let file = std::io::BufReader::new(std::fs::File::open(path)?);
+                colorgrad::GimpGradient::new(
+                    file,
+                    &colorgrad::Color::new(0.0, 0.0, 0.0, 1.0),
+                    &colorgrad::Color::new(1.0, 1.0, 1.0, 1.0),
+                )
+                .map_err(|e| {
+                    PlotError::InvalidData(format!(
+                        "Invalid gradient file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?
+                .boxed()
+            }
+        };
+        Ok(gradient)
+    }
+}
+
+/// Maps the values of a numeric column onto a color gradient
+pub struct GradientScale
+{
+    gradient: Box<dyn Gradient>,
+    pub min: f64,
+    pub max: f64,
+    pub log: bool,
+}
+
+impl GradientScale
+{
+    /// Gradient over the range of `series`, either end can be pinned with `domain`.
+    ///
+    /// A logarithmic scale starts at the smallest positive value.
+    pub fn new(
+        series: &Series,
+        scheme: &GradientScheme,
+        domain: (Option<f64>, Option<f64>),
+        log: bool,
+    ) -> Result<GradientScale, PlotError>
+    {
+        let values = gradient_values(series)?;
+        let data_min = if log
+        {
+            values
+                .into_iter()
+                .flatten()
+                .filter(|&v| v > 0.0)
+                .min_by(f64::total_cmp)
+        }
+        else
+        {
+            values.min()
+        };
+        let min = match domain.0
+        {
+            Some(min) => min,
+            None => data_min.ok_or_else(|| {
+                PlotError::InvalidData("No minimum value in gradient column".to_string())
+            })?,
+        };
+        let max = match domain.1
+        {
+            Some(max) => max,
+            None => values.max().ok_or_else(|| {
+                PlotError::InvalidData("No maximum value in gradient column".to_string())
+            })?,
+        };
+        if log && min <= 0.0
+        {
+            return Err(PlotError::InvalidData(format!(
+                "Logarithmic gradient needs a positive minimum, got {}",
+                min
+            )));
+        }
+        Ok(GradientScale {
+            gradient: scheme.build()?,
+            min,
+            max,
+            log,
+        })
+    }
+
+    /// Relative position of `value` on the gradient, clamped to 0..1
+    pub fn position(&self, value: f64) -> f64
+    {
+        let t = if self.log
+        {
+            (value.max(f64::MIN_POSITIVE).ln() - self.min.ln()) / (self.max.ln() - self.min.ln())
+        }
+        else
+        {
+            (value - self.min) / (self.max - self.min)
+        };
+        if t.is_nan()
+        {
+            0.0
+        }
+        else
+        {
+            t.clamp(0.0, 1.0)
+        }
+    }
+
+    /// Color at relative position `t` in 0..1
+    pub fn color_at_position(&self, t: f64, alpha: f64) -> RGBAColor
+    {
+        let (from, to) = self.gradient.domain();
+        let t = from + (to - from) * t as f32;
+        rbgcolor_from_gradient(self.gradient.at(t).to_rgba8(), alpha)
+    }
+
+    pub fn color_at(&self, value: f64, alpha: f64) -> RGBAColor
+    {
+        self.color_at_position(self.position(value), alpha)
+    }
+}
+
+fn gradient_values(series: &Series) -> Result<Float64Chunked, PlotError>
+{
+    Ok(series
+        .cast(&DataType::Float64)?
+        .f64()
+        .map_err(|_| PlotError::InvalidData("Gradient column is not numeric".to_string()))?
+        .clone())
+}
+
+fn rbgcolor_from_gradient(g: [u8; 4], alpha: f64) -> RGBAColor
+{
+    RGBAColor(g[0], g[1], g[2], alpha)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn integer_categories_follow_color_order()
+    {
+        let palette = Palette::default();
+        let series = Series::new("c".into(), [5, 2, 5, 9]);
+        let facet = Facet::new(&series, &palette, CategoryOrder::Sorted, &[]).unwrap();
+        let names: Vec<&str> = facet
+            .categories
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(names, ["2", "5", "9"]);
+        assert_eq!(facet.indices, [1, 0, 1, 2]);
+        let colors: Vec<RGBColor> = facet.categories.iter().map(|(_, color)| *color).collect();
+        assert_eq!(colors, [palette.pick(0), palette.pick(1), palette.pick(2)]);
+
+        let facet = Facet::new(&series, &palette, CategoryOrder::Appearance, &[]).unwrap();
+        assert_eq!(facet.categories[0], ("5".to_string(), palette.pick(0)));
+        assert_eq!(facet.categories[1], ("2".to_string(), palette.pick(1)));
+    }
+
+    #[test]
+    fn custom_palettes()
+    {
+        let palette: Palette = "#1E88E5, D81B60".parse().unwrap();
+        assert_eq!(palette.pick(0), RGBColor(0x1E, 0x88, 0xE5));
+        assert_eq!(palette.pick(3), RGBColor(0xD8, 0x1B, 0x60));
+        assert!("".parse::<Palette>().is_err());
+        assert_eq!(Palette::Custom(Vec::new()).pick(2), Palette::Palette99.pick(2));
+    }
+}
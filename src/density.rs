@@ -0,0 +1,249 @@
+//! Binning of X/Y points into a grid of rectangles or hexagons for density plots.
+
+use std::collections::HashMap;
+
+/// Size of the cells points are counted in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellSize
+{
+    /// cells along the X range, unless `width` is given
+    pub count: usize,
+    /// cell width and height in data units, decades on a logarithmic axis
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    /// height over width of the plotting area, so that default cells look regular
+    pub aspect: f64,
+}
+
+/// A cell with at least one point in it
+#[derive(Debug, Clone)]
+pub struct Cell
+{
+    /// corners in data units
+    pub outline: Vec<(f64, f64)>,
+    pub count: f64,
+}
+
+/// Counts the finite points per cell of a rectangular grid, or of a hexagonal one with `hex`.
+///
+/// On a logarithmic axis the cells are evenly sized in log space, non-positive values are left out.
+pub fn cells(points: &[(f64, f64)], size: CellSize, hex: bool, log: (bool, bool)) -> Vec<Cell>
+{
+    let scale = |v: f64, log: bool| if log { v.log10() } else { v };
+    let unscale = |v: f64, log: bool| if log { 10f64.powf(v) } else { v };
+    let points: Vec<(f64, f64)> = points
+        .iter()
+        .filter(|(x, y)| (!log.0 || *x > 0.0) && (!log.1 || *y > 0.0))
+        .map(|&(x, y)| (scale(x, log.0), scale(y, log.1)))
+        .filter(|(x, y)| x.is_finite() && y.is_finite())
+        .collect();
+    if points.is_empty()
+    {
+        return Vec::new();
+    }
+    let (x_min, x_max, y_min, y_max) = points.iter().fold(
+        (f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY, f64::NEG_INFINITY),
+        |(x0, x1, y0, y1), &(x, y)| (x0.min(x), x1.max(x), y0.min(y), y1.max(y)),
+    );
+
+    let count = match size.width
+    {
+        Some(width) => ((x_max - x_min) / width).max(1.0),
+        None => size.count.max(1) as f64,
+    };
+    // regular hexagons are sqrt(3) times as high as wide between their rows
+    let rows = if hex
+    {
+        count * size.aspect / 3f64.sqrt()
+    }
+    else
+    {
+        count * size.aspect
+    }
+    .round()
+    .max(1.0);
+    let width = size
+        .width
+        .unwrap_or((x_max - x_min) / count)
+        .max(f64::EPSILON);
+    let height = size
+        .height
+        .unwrap_or((y_max - y_min) / rows)
+        .max(f64::EPSILON);
+
+    let mut counts: HashMap<(i64, i64, bool), f64> = HashMap::new();
+    for (x, y) in points
+    {
+        let (u, v) = ((x - x_min) / width, (y - y_min) / height);
+        let key = if hex
+        {
+            // nearest center of two interleaved lattices
+            let (u1, v1) = (u.round(), v.round());
+            let (u2, v2) = (u.floor(), v.floor());
+            let d1 = (u - u1).powi(2) + 3.0 * (v - v1).powi(2);
+            let d2 = (u - u2 - 0.5).powi(2) + 3.0 * (v - v2 - 0.5).powi(2);
+            if d1 <= d2
+            {
+                (u1 as i64, v1 as i64, false)
+            }
+            else
+            {
+                (u2 as i64, v2 as i64, true)
+            }
+        }
+        else
+        {
+            (u.floor() as i64, v.floor() as i64, false)
+        };
+        *counts.entry(key).or_default() += 1.0;
+    }
+
+    let mut cells: Vec<Cell> = counts
+        .into_iter()
+        .map(|((i, j, offset), count)| {
+            let corners: Vec<(f64, f64)> = if hex
+            {
+                let shift = if offset { 0.5 } else { 0.0 };
+                let (cu, cv) = (i as f64 + shift, j as f64 + shift);
+                [
+                    (0.5, -0.5),
+                    (0.5, 0.5),
+                    (0.0, 1.0),
+                    (-0.5, 0.5),
+                    (-0.5, -0.5),
+                    (0.0, -1.0),
+                ]
+                .iter()
+                .map(|(du, dv)| (cu + du, cv + dv / 3.0))
+                .collect()
+            }
+            else
+            {
+                let (u, v) = (i as f64, j as f64);
+                vec![(u, v), (u + 1.0, v), (u + 1.0, v + 1.0), (u, v + 1.0)]
+            };
+            let outline = corners
+                .into_iter()
+                .map(|(u, v)| {
+                    (unscale(x_min + u * width, log.0), unscale(y_min + v * height, log.1))
+                })
+                .collect();
+            Cell { outline, count }
+        })
+        .collect();
+    // denser cells on top where hexagons touch
+    cells.sort_by(|a, b| a.count.total_cmp(&b.count));
+    cells
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const UNIT: CellSize = CellSize {
+        count: 1,
+        width: Some(1.0),
+        height: Some(1.0),
+        aspect: 1.0,
+    };
+
+    /// Count per cell center, in tenths of the data units
+    fn counts(cells: &[Cell]) -> HashMap<(i64, i64), f64>
+    {
+        cells
+            .iter()
+            .map(|cell| {
+                let n = cell.outline.len() as f64;
+                let (x, y) = cell
+                    .outline
+                    .iter()
+                    .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+                (((x / n * 10.0).round() as i64, (y / n * 10.0).round() as i64), cell.count)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rectangle_counts()
+    {
+        let points = [
+            (0.0, 0.0),
+            (0.5, 0.5),
+            (1.5, 0.2),
+            (0.3, 1.2),
+            (1.2, 1.7),
+            (4.0, 2.0),
+        ];
+        let cells = cells(&points, UNIT, false, (false, false));
+        let expected = HashMap::from([
+            ((5, 5), 2.0),
+            ((15, 5), 1.0),
+            ((5, 15), 1.0),
+            ((15, 15), 1.0),
+            ((45, 25), 1.0),
+        ]);
+        assert_eq!(counts(&cells), expected);
+        // the densest cell is drawn last
+        assert_eq!(cells.last().unwrap().count, 2.0);
+        assert_eq!(cells.last().unwrap().outline, [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+
+        // four columns over the X range, rows by the aspect ratio
+        let size = CellSize {
+            count: 4,
+            width: None,
+            height: None,
+            aspect: 0.5,
+        };
+        let cells = super::cells(&points, size, false, (false, false));
+        assert_eq!(counts(&cells), expected);
+    }
+
+    #[test]
+    fn hexagon_boundaries()
+    {
+        // (0.25, 0.25) lies on the edge between the cells around (0, 0) and (0.5, 0.5),
+        // (0.5, 0) on the one between (0, 0) and (1, 0)
+        let points = [(0.0, 0.0), (0.25, 0.25), (0.5, 0.0), (0.5, 0.5), (1.0, 1.0)];
+        let cells = cells(&points, UNIT, true, (false, false));
+        assert!(cells.iter().all(|cell| cell.outline.len() == 6));
+        assert_eq!(
+            counts(&cells),
+            HashMap::from([
+                ((0, 0), 2.0),
+                ((10, 0), 1.0),
+                ((5, 5), 1.0),
+                ((10, 10), 1.0)
+            ])
+        );
+        // every point is counted exactly once
+        assert_eq!(cells.iter().map(|cell| cell.count).sum::<f64>(), points.len() as f64);
+    }
+
+    #[test]
+    fn logarithmic_cells()
+    {
+        let points = [
+            (1.0, 10.0),
+            (10.0, 100.0),
+            (100.0, 1000.0),
+            (0.0, 5.0),
+            (-1.0, 5.0),
+        ];
+        let cells = cells(&points, UNIT, false, (true, true));
+        // non-positive values are left out, the others get a decade each
+        assert_eq!(cells.len(), 3);
+        assert!(cells.iter().all(|cell| cell.count == 1.0));
+        let mut first = cells
+            .iter()
+            .map(|cell| cell.outline.clone())
+            .min_by(|a, b| a[0].0.total_cmp(&b[0].0))
+            .unwrap();
+        first
+            .iter_mut()
+            .for_each(|(x, y)| (*x, *y) = (x.round(), y.round()));
+        assert_eq!(first, [(1.0, 10.0), (10.0, 10.0), (10.0, 100.0), (1.0, 100.0)]);
+
+        assert!(super::cells(&[(0.0, 1.0)], UNIT, true, (true, false)).is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+use polars::prelude::PolarsError;
+use std::error::Error;
+
+#[derive(Debug)]
+pub enum PlotError
+{
+    IoError(std::io::Error),
+    PolarsError(PolarsError),
+    HexDecodeError(hex::FromHexError),
+    InvalidColumn(String),
+    InvalidData(String),
+}
+
+impl std::fmt::Display for PlotError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            PlotError::IoError(e) => write!(f, "IO error: {}", e),
+            PlotError::PolarsError(e) => write!(f, "Data processing error: {}", e),
+            PlotError::HexDecodeError(e) => write!(f, "Invalid color format: {}", e),
+            PlotError::InvalidColumn(msg) => write!(f, "Invalid column: {}", msg),
+            PlotError::InvalidData(msg) => write!(f, "Invalid data: {}", msg),
+        }
+    }
+}
+
+impl Error for PlotError {}
+
+impl From<std::io::Error> for PlotError
+{
+    fn from(error: std::io::Error) -> Self
+    {
+        PlotError::IoError(error)
+    }
+}
+
+impl From<PolarsError> for PlotError
+{
+    fn from(error: PolarsError) -> Self
+    {
+        PlotError::PolarsError(error)
+    }
+}
+
+impl From<hex::FromHexError> for PlotError
+{
+    fn from(error: hex::FromHexError) -> Self
+    {
+        PlotError::HexDecodeError(error)
+    }
+}
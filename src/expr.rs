@@ -0,0 +1,922 @@
+//! Expressions over the columns of a DataFrame, parsed into polars lazy expressions.
+//!
+//! Columns are referenced by header name, by `colN` or `$N` with a 1-based index, or by a
+//! name in backticks like `` `run time` ``. Strings are quoted with `'` or `"`, a backslash
+//! escapes the next character.
+//! Numbers combine with `+ - * / % ^` and the functions in [`FUNCTIONS`].
+
+use crate::PlotError;
+
+use polars::prelude::*;
+use polars::series::ops::NullBehavior;
+
+/// Keeps the rows of `df` for which all `filters` hold, like `"col3 > 10 && status == 'ok'"`.
+///
+/// Rows where a condition is missing, e.g. compares a missing value, are dropped.
+pub fn filter_rows(df: DataFrame, filters: &[String]) -> Result<DataFrame, PlotError>
+{
+    if filters.is_empty()
+    {
+        return Ok(df);
+    }
+    let conditions = filters
+        .iter()
+        .map(|filter| parse_filter(&df, filter))
+        .collect::<Result<Vec<_>, PlotError>>()?;
+    let mut rows = df.lazy();
+    for condition in conditions
+    {
+        rows = rows.filter(condition);
+    }
+    Ok(rows.collect()?)
+}
+
+/// Parses a condition over the columns of `df`.
+///
+/// Comparisons `== != < <= > >=` combine with `&&`, `||`, `!` and parentheses,
+/// `== null` and `!= null` test for missing values.
+pub fn parse_filter(df: &DataFrame, text: &str) -> Result<Expr, PlotError>
+{
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+        df,
+        text,
+        role: "Expression",
+    };
+    let condition = parser.or()?;
+    parser.expect_end()?;
+    parser.condition(condition)
+}
+
+/// Functions of one number column, e.g. `log2(fold_change)` or `cumsum(bytes)`
+pub const FUNCTIONS: [&str; 9] = [
+    "log", "log2", "log10", "exp", "abs", "sqrt", "cumsum", "diff", "rank",
+];
+
+/// Resolves a column reference, or adds a column computed by an expression like `bytes / seconds`.
+///
+/// Returns the reference for the column, a computed column is named by the expression text,
+/// which also becomes the default axis label. With `group`, cumulative sums, differences and
+/// ranks restart for each value of that column.
+pub fn derive_column(
+    df: &mut DataFrame,
+    reference: &str,
+    role: &str,
+    group: Option<&str>,
+) -> Result<String, PlotError>
+{
+    let error = match crate::column_index(df, reference, role)
+    {
+        Ok(_) => return Ok(reference.to_string()),
+        Err(error) => error,
+    };
+    // a lone name or number is a missing column, not an expression
+    if tokenize(reference)?.len() <= 2
+    {
+        return Err(error);
+    }
+    let name = reference.trim();
+    let mut parser = Parser {
+        tokens: tokenize(reference)?,
+        position: 0,
+        df,
+        text: reference,
+        role,
+    };
+    let typed = parser.or()?;
+    parser.expect_end()?;
+    let expr = match typed.kind
+    {
+        Kind::Number | Kind::Boolean => typed.expr,
+        kind =>
+        {
+            return Err(PlotError::InvalidData(format!(
+                "Invalid expression {}: {} column must be a number, not a {}",
+                reference, role, kind
+            )))
+        }
+    };
+    let expr = match group
+    {
+        Some(group) => expr.over([col(group)]),
+        None => expr,
+    };
+    *df = df.clone().lazy().with_column(expr.alias(name)).collect()?;
+    Ok(name.to_string())
+}
+
+/// Resolves a list of columns like [`crate::column_list`], parts that are no columns are
+/// computed by [`derive_column`], e.g. `bytes / seconds,log2(fold_change)`
+pub fn derive_list(
+    df: &mut DataFrame,
+    list: &str,
+    role: &str,
+    group: Option<&str>,
+) -> Result<String, PlotError>
+{
+    let mut references = Vec::new();
+    for part in list.split(',').map(str::trim)
+    {
+        let range = part.split_once('-').is_some_and(|(first, last)| {
+            first.trim().parse::<usize>().is_ok() && last.trim().parse::<usize>().is_ok()
+        });
+        match crate::column_list(df, part, role)
+        {
+            Ok(_) => references.push(part.to_string()),
+            Err(error) if range => return Err(error),
+            Err(_) => references.push(derive_column(df, part, role, group)?),
+        }
+    }
+    Ok(references.join(","))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token
+{
+    Number(f64),
+    Str(String),
+    Name(String),
+    /// backtick quoted name or `$N`, never a keyword
+    Column(String),
+    Operator(&'static str),
+    Open,
+    Close,
+    End,
+}
+
+impl std::fmt::Display for Token
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Str(string) => write!(f, "'{}'", string),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Column(name) => write!(f, "`{}`", name),
+            Token::Operator(operator) => write!(f, "{}", operator),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::End => write!(f, "end of expression"),
+        }
+    }
+}
+
+/// Names and indices of the columns `text` refers to, as written, empty if it does not parse
+pub(crate) fn column_tokens(text: &str) -> Vec<String>
+{
+    tokenize(text)
+        .map(|tokens| {
+            tokens
+                .into_iter()
+                .filter_map(|(_, token)| match token
+                {
+                    Token::Name(name) | Token::Column(name) => Some(name),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+const OPERATORS: [&str; 16] = [
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "=", "+", "-", "*", "/", "%", "^",
+];
+
+/// Splits `text` into tokens, each with its 1-based character position for error messages
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, PlotError>
+{
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len()
+    {
+        let start = i;
+        let c = chars[i];
+        let token = if c.is_whitespace()
+        {
+            i += 1;
+            continue;
+        }
+        else if c == '('
+        {
+            i += 1;
+            Token::Open
+        }
+        else if c == ')'
+        {
+            i += 1;
+            Token::Close
+        }
+        else if c == '\'' || c == '"' || c == '`'
+        {
+            // a backslash takes the next character as is, e.g. 'it\'s'
+            let mut quoted = String::new();
+            let mut end = None;
+            let mut j = i + 1;
+            while j < chars.len()
+            {
+                match chars[j]
+                {
+                    '\\' if j + 1 < chars.len() =>
+                    {
+                        quoted.push(chars[j + 1]);
+                        j += 2;
+                    }
+                    d if d == c =>
+                    {
+                        end = Some(j);
+                        break;
+                    }
+                    d =>
+                    {
+                        quoted.push(d);
+                        j += 1;
+                    }
+                }
+            }
+            let end =
+                end.ok_or_else(|| syntax_error(text, start + 1, &format!("unclosed {}", c)))?;
+            i = end + 1;
+            if c == '`'
+            {
+                Token::Column(quoted)
+            }
+            else
+            {
+                Token::Str(quoted)
+            }
+        }
+        else if c == '$'
+        {
+            let length = chars[i + 1..]
+                .iter()
+                .take_while(|d| d.is_ascii_digit())
+                .count();
+            if length == 0
+            {
+                return Err(syntax_error(text, start + 1, "expected a column index after $"));
+            }
+            i += 1 + length;
+            Token::Column(chars[start + 1..i].iter().collect())
+        }
+        else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric()
+                    || chars[i] == '.'
+                    || ((chars[i] == '+' || chars[i] == '-') && matches!(chars[i - 1], 'e' | 'E')))
+            {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            Token::Number(number.parse().map_err(|_| {
+                syntax_error(text, start + 1, &format!("invalid number {}", number))
+            })?)
+        }
+        else if c.is_alphabetic() || c == '_'
+        {
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.'))
+            {
+                i += 1;
+            }
+            Token::Name(chars[start..i].iter().collect())
+        }
+        else
+        {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            match OPERATORS
+                .iter()
+                .find(|operator| rest.starts_with(*operator))
+            {
+                Some(&"=") => return Err(syntax_error(text, start + 1, "use == to compare")),
+                Some(operator) =>
+                {
+                    i += operator.len();
+                    Token::Operator(operator)
+                }
+                None => return Err(syntax_error(text, start + 1, &format!("unexpected {}", c))),
+            }
+        };
+        tokens.push((start + 1, token));
+    }
+    tokens.push((chars.len() + 1, Token::End));
+    Ok(tokens)
+}
+
+fn syntax_error(text: &str, position: usize, message: &str) -> PlotError
+{
+    PlotError::InvalidData(format!(
+        "Invalid expression {}: {} at character {}",
+        text, message, position
+    ))
+}
+
+/// What a value can be compared with
+#[derive(Debug, Clone, PartialEq)]
+enum Kind
+{
+    Number,
+    Text,
+    Boolean,
+    /// the `null` literal
+    Null,
+    /// e.g. dates or lists, not comparable in expressions
+    Other(DataType),
+}
+
+impl std::fmt::Display for Kind
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            Kind::Number => write!(f, "number"),
+            Kind::Text => write!(f, "string"),
+            Kind::Boolean => write!(f, "boolean"),
+            Kind::Null => write!(f, "null"),
+            Kind::Other(dtype) => write!(f, "{}", dtype),
+        }
+    }
+}
+
+/// A parsed expression, `text` describes it in error messages
+struct Typed
+{
+    expr: Expr,
+    kind: Kind,
+    text: String,
+}
+
+struct Parser<'a>
+{
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    df: &'a DataFrame,
+    text: &'a str,
+    /// names the columns in error messages
+    role: &'a str,
+}
+
+impl Parser<'_>
+{
+    fn peek(&self) -> &Token
+    {
+        &self.tokens[self.position].1
+    }
+
+    fn next(&mut self) -> Token
+    {
+        let token = self.tokens[self.position].1.clone();
+        if token != Token::End
+        {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error(&self, message: &str) -> PlotError
+    {
+        syntax_error(self.text, self.tokens[self.position].0, message)
+    }
+
+    fn expect_end(&self) -> Result<(), PlotError>
+    {
+        match self.peek()
+        {
+            Token::End => Ok(()),
+            token => Err(self.error(&format!("unexpected {}", token))),
+        }
+    }
+
+    /// Checks that `typed` is true or false for each row
+    fn condition(&self, typed: Typed) -> Result<Expr, PlotError>
+    {
+        match typed.kind
+        {
+            Kind::Boolean => Ok(typed.expr),
+            kind => Err(PlotError::InvalidData(format!(
+                "Invalid expression {}: {} is a {}, not a condition",
+                self.text, typed.text, kind
+            ))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Typed, PlotError>
+    {
+        let mut left = self.and()?;
+        while self.peek() == &Token::Operator("||")
+        {
+            self.next();
+            let right = self.and()?;
+            left = self.logical(left, right, "||")?;
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Typed, PlotError>
+    {
+        let mut left = self.not()?;
+        while self.peek() == &Token::Operator("&&")
+        {
+            self.next();
+            let right = self.not()?;
+            left = self.logical(left, right, "&&")?;
+        }
+        Ok(left)
+    }
+
+    fn logical(&self, left: Typed, right: Typed, operator: &str) -> Result<Typed, PlotError>
+    {
+        let text = format!("{} {} {}", left.text, operator, right.text);
+        let (left, right) = (self.condition(left)?, self.condition(right)?);
+        Ok(Typed {
+            expr: if operator == "&&"
+            {
+                left.and(right)
+            }
+            else
+            {
+                left.or(right)
+            },
+            kind: Kind::Boolean,
+            text,
+        })
+    }
+
+    fn not(&mut self) -> Result<Typed, PlotError>
+    {
+        if self.peek() == &Token::Operator("!")
+        {
+            self.next();
+            let operand = self.not()?;
+            let text = format!("!{}", operand.text);
+            return Ok(Typed {
+                expr: self.condition(operand)?.not(),
+                kind: Kind::Boolean,
+                text,
+            });
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Typed, PlotError>
+    {
+        let left = self.sum()?;
+        let operator = match self.peek()
+        {
+            Token::Operator(operator @ ("==" | "!=" | "<" | "<=" | ">" | ">=")) => *operator,
+            _ => return Ok(left),
+        };
+        self.next();
+        let right = self.sum()?;
+        let text = format!("{} {} {}", left.text, operator, right.text);
+
+        let expr = match (&left.kind, &right.kind)
+        {
+            (Kind::Null, Kind::Null) =>
+            {
+                return Err(PlotError::InvalidData(format!(
+                    "Invalid expression {}: {} compares null with null",
+                    self.text, text
+                )))
+            }
+            (Kind::Null, _) | (_, Kind::Null) =>
+            {
+                let value = if left.kind == Kind::Null
+                {
+                    right.expr
+                }
+                else
+                {
+                    left.expr
+                };
+                match operator
+                {
+                    "==" => value.is_null(),
+                    "!=" => value.is_not_null(),
+                    _ =>
+                    {
+                        return Err(PlotError::InvalidData(format!(
+                            "Invalid expression {}: {} orders by null, only == null and != null test for missing values",
+                            self.text, text
+                        )))
+                    }
+                }
+            }
+            (Kind::Number, Kind::Number)
+            | (Kind::Text, Kind::Text)
+            | (Kind::Boolean, Kind::Boolean) => match operator
+            {
+                "==" => left.expr.eq(right.expr),
+                "!=" => left.expr.neq(right.expr),
+                "<" => left.expr.lt(right.expr),
+                "<=" => left.expr.lt_eq(right.expr),
+                ">" => left.expr.gt(right.expr),
+                _ => left.expr.gt_eq(right.expr),
+            },
+            (left_kind, right_kind) =>
+            {
+                return Err(PlotError::InvalidData(format!(
+                    "Type mismatch in expression {}: {} {} compared with {} {}",
+                    self.text, left_kind, left.text, right_kind, right.text
+                )))
+            }
+        };
+        Ok(Typed {
+            expr,
+            kind: Kind::Boolean,
+            text,
+        })
+    }
+
+    fn sum(&mut self) -> Result<Typed, PlotError>
+    {
+        let mut left = self.product()?;
+        while let Token::Operator(operator @ ("+" | "-")) = *self.peek()
+        {
+            self.next();
+            let right = self.product()?;
+            left = self.arithmetic(left, right, operator)?;
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Typed, PlotError>
+    {
+        let mut left = self.negation()?;
+        while let Token::Operator(operator @ ("*" | "/" | "%")) = *self.peek()
+        {
+            self.next();
+            let right = self.negation()?;
+            left = self.arithmetic(left, right, operator)?;
+        }
+        Ok(left)
+    }
+
+    fn negation(&mut self) -> Result<Typed, PlotError>
+    {
+        if self.peek() == &Token::Operator("-")
+        {
+            self.next();
+            let operand = self.negation()?;
+            let text = format!("-{}", operand.text);
+            let operand = self.number(operand, &text)?;
+            return Ok(Typed {
+                expr: -operand,
+                kind: Kind::Number,
+                text,
+            });
+        }
+        self.power()
+    }
+
+    /// `^` binds tighter than a leading minus and groups to the right, -2^2 is -4
+    fn power(&mut self) -> Result<Typed, PlotError>
+    {
+        let base = self.operand()?;
+        if self.peek() != &Token::Operator("^")
+        {
+            return Ok(base);
+        }
+        self.next();
+        let exponent = self.negation()?;
+        self.arithmetic(base, exponent, "^")
+    }
+
+    fn arithmetic(&self, left: Typed, right: Typed, operator: &str) -> Result<Typed, PlotError>
+    {
+        let text = format!("{} {} {}", left.text, operator, right.text);
+        let (left, right) = (self.number(left, &text)?, self.number(right, &text)?);
+        Ok(Typed {
+            expr: match operator
+            {
+                "+" => left + right,
+                "-" => left - right,
+                "*" => left * right,
+                "/" => binary_expr(left, Operator::TrueDivide, right),
+                "%" => left % right,
+                _ => left.cast(DataType::Float64).pow(right),
+            },
+            kind: Kind::Number,
+            text,
+        })
+    }
+
+    /// Checks that `typed` is a number, `context` is the expression using it
+    fn number(&self, typed: Typed, context: &str) -> Result<Expr, PlotError>
+    {
+        match typed.kind
+        {
+            Kind::Number => Ok(typed.expr),
+            kind => Err(PlotError::InvalidData(format!(
+                "Type mismatch in expression {}: {} {} used as a number in {}",
+                self.text, kind, typed.text, context
+            ))),
+        }
+    }
+
+    /// A function of one number like `log2(fold_change)`, positioned at the name
+    fn function(&mut self, name: &str) -> Result<Typed, PlotError>
+    {
+        if !FUNCTIONS.contains(&name)
+        {
+            return Err(self.error(&format!(
+                "unknown function {}, expected one of {}",
+                name,
+                FUNCTIONS.join(", ")
+            )));
+        }
+        self.next();
+        self.next();
+        let argument = self.or()?;
+        if self.peek() != &Token::Close
+        {
+            return Err(self.error("expected )"));
+        }
+        self.next();
+        let text = format!("{}({})", name, argument.text);
+        let argument = self.number(argument, &text)?;
+        let expr = match name
+        {
+            "log" => argument.log(std::f64::consts::E),
+            "log2" => argument.log(2.0),
+            "log10" => argument.log(10.0),
+            "exp" => argument.exp(),
+            "abs" => argument.abs(),
+            "sqrt" => argument.cast(DataType::Float64).sqrt(),
+            "cumsum" => argument.cum_sum(false),
+            "diff" => argument.diff(lit(1), NullBehavior::Ignore),
+            _ => argument.rank(
+                RankOptions {
+                    method: RankMethod::Average,
+                    descending: false,
+                },
+                None,
+            ),
+        };
+        Ok(Typed {
+            expr,
+            kind: Kind::Number,
+            text,
+        })
+    }
+
+    fn operand(&mut self) -> Result<Typed, PlotError>
+    {
+        let token = self.peek().clone();
+        let typed = match token
+        {
+            Token::Open =>
+            {
+                self.next();
+                let inner = self.or()?;
+                if self.peek() != &Token::Close
+                {
+                    return Err(self.error("expected )"));
+                }
+                Typed {
+                    text: format!("({})", inner.text),
+                    ..inner
+                }
+            }
+            Token::Number(number) => Typed {
+                expr: lit(number),
+                kind: Kind::Number,
+                text: token.to_string(),
+            },
+            Token::Str(ref string) => Typed {
+                expr: lit(string.as_str()),
+                kind: Kind::Text,
+                text: token.to_string(),
+            },
+            Token::Name(ref name) if name == "true" || name == "false" => Typed {
+                expr: lit(name == "true"),
+                kind: Kind::Boolean,
+                text: name.clone(),
+            },
+            Token::Name(ref name) if name == "null" => Typed {
+                expr: lit(NULL),
+                kind: Kind::Null,
+                text: name.clone(),
+            },
+            Token::Name(ref name) if self.tokens[self.position + 1].1 == Token::Open =>
+            {
+                return self.function(name)
+            }
+            Token::Name(ref name) => self.column(name)?,
+            Token::Column(ref name) => self.column(name)?,
+            token =>
+            {
+                return Err(self.error(&format!("expected a column or value, found {}", token)))
+            }
+        };
+        self.next();
+        Ok(typed)
+    }
+
+    /// A header name, `colN` or a 1-based index
+    fn column(&self, reference: &str) -> Result<Typed, PlotError>
+    {
+        let index = match crate::column_index(self.df, reference, self.role)
+        {
+            Ok(index) => index,
+            Err(error) => match reference
+                .strip_prefix("col")
+                .map(|index| index.parse::<usize>())
+            {
+                Some(Ok(index)) => crate::column_index(self.df, &index.to_string(), self.role)
+                    .map_err(|_| error)?,
+                _ => return Err(error),
+            },
+        };
+        let series = crate::column(self.df, index, self.role)?;
+        let expr = col(series.name().as_str());
+        let (expr, kind) = match series.dtype()
+        {
+            dtype if dtype.is_primitive_numeric() => (expr, Kind::Number),
+            DataType::String => (expr, Kind::Text),
+            DataType::Categorical(_, _) | DataType::Enum(_, _) =>
+            {
+                (expr.cast(DataType::String), Kind::Text)
+            }
+            DataType::Boolean => (expr, Kind::Boolean),
+            dtype => (expr, Kind::Other(dtype.clone())),
+        };
+        Ok(Typed {
+            expr,
+            kind,
+            text: series.name().to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn table() -> DataFrame
+    {
+        df!(
+            "a" => [10.0, 1.0, 4.0],
+            "b" => [3.0, 10.0, 6.0],
+            "c" => [2.0, 1.0, 7.0],
+            "run time" => [1, 2, 3],
+            "name" => ["it's", "say \"hi\"", "x"],
+            "n" => [Some(1.0), None, Some(3.0)]
+        )
+        .unwrap()
+    }
+
+    /// Values of the numeric expression `text` for each row
+    fn evaluate(text: &str) -> Result<Vec<Option<f64>>, PlotError>
+    {
+        let mut df = table();
+        let name = derive_column(&mut df, text, "Y", None)?;
+        let values = df.column(&name)?.cast(&DataType::Float64)?;
+        Ok(values.f64()?.into_iter().collect())
+    }
+
+    /// Row numbers kept by the filter `text`
+    fn kept(text: &str) -> Result<Vec<i32>, PlotError>
+    {
+        let df = table().with_row_index("row".into(), None)?;
+        let rows = filter_rows(df, &[text.to_string()])?;
+        Ok(rows
+            .column("row")?
+            .cast(&DataType::Int32)?
+            .i32()?
+            .into_no_null_iter()
+            .collect())
+    }
+
+    fn message(result: Result<impl std::fmt::Debug, PlotError>) -> String
+    {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn precedence_and_associativity()
+    {
+        assert_eq!(evaluate("-2^2 + 0 * a").unwrap()[0], Some(-4.0));
+        assert_eq!(evaluate("2^3^2 + 0 * a").unwrap()[0], Some(512.0));
+        assert_eq!(evaluate("a - b - c").unwrap(), [Some(5.0), Some(-10.0), Some(-9.0)]);
+        assert_eq!(evaluate("a + b * c").unwrap()[0], Some(16.0));
+        assert_eq!(evaluate("(a + b) * c").unwrap()[0], Some(26.0));
+        assert_eq!(evaluate("a / b / c").unwrap()[1], Some(0.1));
+        assert_eq!(evaluate("a % 4 + 1").unwrap()[0], Some(3.0));
+    }
+
+    #[test]
+    fn logical_precedence()
+    {
+        // && binds tighter than ||, both looser than comparisons
+        assert_eq!(kept("a > 5 || b > 5 && c > 5").unwrap(), [0, 2]);
+        assert_eq!(kept("(a > 5 || b > 5) && c > 5").unwrap(), [2]);
+        assert_eq!(kept("!a > 5").unwrap(), [1, 2]);
+        assert_eq!(kept("a + 1 > b * 2").unwrap(), [0]);
+    }
+
+    #[test]
+    fn null_comparisons()
+    {
+        assert_eq!(kept("n == null").unwrap(), [1]);
+        assert_eq!(kept("null != n").unwrap(), [0, 2]);
+        // a missing value compares as neither true nor false
+        assert_eq!(kept("n > 0").unwrap(), [0, 2]);
+        assert!(message(kept("n < null")).contains("only == null and != null"));
+        assert!(message(kept("null == null")).contains("compares null with null"));
+    }
+
+    #[test]
+    fn column_references()
+    {
+        let expected = [Some(14.0), Some(13.0), Some(13.0)];
+        assert_eq!(evaluate("col1 + $2 + `run time`").unwrap(), expected);
+        assert_eq!(evaluate("a + b + `run time`").unwrap(), expected);
+        assert!(message(evaluate("col9 + 1")).contains("col9 not found"));
+        assert!(message(evaluate("$ + 1")).contains("expected a column index after $"));
+    }
+
+    #[test]
+    fn string_literals()
+    {
+        assert_eq!(kept(r"name == 'it\'s'").unwrap(), [0]);
+        assert_eq!(kept(r#"name == "say \"hi\"""#).unwrap(), [1]);
+        assert_eq!(kept(r#"name != 'x' && name > "j""#).unwrap(), [1]);
+        assert!(message(kept("name == 'x")).contains("unclosed '"));
+    }
+
+    #[test]
+    fn type_mismatch()
+    {
+        let error = message(evaluate(r#""a" + 1"#));
+        assert!(error.contains("Type mismatch"), "{}", error);
+        assert!(error.contains("string 'a' used as a number"), "{}", error);
+        assert!(message(kept("name > 1")).contains("string name compared with number 1"));
+        assert!(message(kept("a + 1")).contains("not a condition"));
+    }
+
+    #[test]
+    fn syntax_errors()
+    {
+        assert!(message(evaluate("(a + 1")).contains("expected ) at character 7"));
+        assert!(message(evaluate("a + 1)")).contains("unexpected ) at character 6"));
+        assert!(message(evaluate("log2(a")).contains("expected )"));
+        let error = message(evaluate("foo(a) + 1"));
+        assert!(error.contains("unknown function foo, expected one of log, log2"), "{}", error);
+        assert!(message(kept("a = 1")).contains("use == to compare"));
+    }
+
+    #[test]
+    fn ranges_and_subtraction()
+    {
+        let mut df = table();
+        // numbers around a minus are a column range, names an expression
+        assert_eq!(derive_list(&mut df, "2-5", "Y", None).unwrap(), "2-5");
+        assert_eq!(derive_list(&mut df, "a-b, 1", "Y", None).unwrap(), "a-b,1");
+        let values: Vec<Option<f64>> = df
+            .column("a-b")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(values, [Some(7.0), Some(-9.0), Some(-2.0)]);
+        assert!(message(derive_list(&mut df, "2-9", "Y", None)).contains("not a column range"));
+        assert_eq!(derive_list(&mut df, "a - 2", "Y", None).unwrap(), "a - 2");
+
+        // a header name wins over the expression
+        let mut df = df!("a" => [1], "b" => [2], "a-b" => [5]).unwrap();
+        assert_eq!(derive_list(&mut df, "a-b", "Y", None).unwrap(), "a-b");
+        assert_eq!(df.width(), 3);
+        assert!(message(derive_column(&mut df, "z", "X", None)).contains("X column z not found"));
+    }
+
+    #[test]
+    fn grouped_running_functions()
+    {
+        let values = |df: &DataFrame, name: &str| -> Vec<Option<f64>> {
+            let column = df.column(name).unwrap().cast(&DataType::Float64).unwrap();
+            column.f64().unwrap().into_iter().collect()
+        };
+        let table = || df!("v" => [1, 2, 3, 4], "file" => ["f1", "f1", "f2", "f2"]).unwrap();
+
+        let mut df = table();
+        let name = derive_column(&mut df, "cumsum(v)", "Y", None).unwrap();
+        assert_eq!(values(&df, &name), [Some(1.0), Some(3.0), Some(6.0), Some(10.0)]);
+
+        // overlaid files restart at each file
+        let mut df = table();
+        let name = derive_column(&mut df, "cumsum(v)", "Y", Some("file")).unwrap();
+        assert_eq!(values(&df, &name), [Some(1.0), Some(3.0), Some(3.0), Some(7.0)]);
+        let name = derive_column(&mut df, "diff(v) * 2", "Y", Some("file")).unwrap();
+        assert_eq!(values(&df, &name), [None, Some(2.0), None, Some(2.0)]);
+        let list = derive_list(&mut df, "rank(v), v", "Y", Some("file")).unwrap();
+        assert_eq!(list, "rank(v),v");
+        assert_eq!(values(&df, "rank(v)"), [Some(1.0), Some(2.0), Some(1.0), Some(2.0)]);
+    }
+}
@@ -0,0 +1,512 @@
+//! Regression and smoothing curves over the plotted points.
+
+use crate::PlotError;
+
+use std::str::FromStr;
+
+/// Curve fitted through the X/Y points of a series, or of each color facet category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit
+{
+    /// least squares line
+    Linear,
+    /// least squares polynomial of the given degree
+    Poly(usize),
+    /// locally weighted linear regression over the nearest three quarters of the points
+    Loess,
+    /// y = a e^(b x), fitted as a line through the logarithms of the positive Y values
+    Exp,
+}
+
+impl FromStr for Fit
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "linear" => Ok(Fit::Linear),
+            "loess" => Ok(Fit::Loess),
+            "exp" => Ok(Fit::Exp),
+            _ => match s.strip_prefix("poly:").map(str::parse::<usize>)
+            {
+                Some(Ok(degree)) if (1..=MAX_DEGREE).contains(&degree) => Ok(Fit::Poly(degree)),
+                Some(_) =>
+                {
+                    Err(format!("invalid polynomial degree in {}, expected 1 to {}", s, MAX_DEGREE))
+                }
+                None => Err(format!("unknown fit {}, expected linear, poly:N, loess or exp", s)),
+            },
+        }
+    }
+}
+
+const MAX_DEGREE: usize = 10;
+
+/// Share of the points each local regression of a loess fit uses
+const LOESS_SPAN: f64 = 0.75;
+
+/// Points along the X range the fitted curve is sampled at
+const SAMPLES: usize = 200;
+
+/// Fitted Y at an X
+type Predict = Box<dyn Fn(f64) -> f64>;
+
+/// A fitted curve with its description
+#[derive(Debug, Clone)]
+pub struct Fitted
+{
+    /// sampled along the X range of the points
+    pub curve: Vec<(f64, f64)>,
+    /// polynomial coefficients from the constant up, or a and b of an exponential
+    pub coefficients: Vec<f64>,
+    /// coefficient of determination against the Y values
+    pub r_squared: f64,
+    fit: Fit,
+}
+
+impl Fitted
+{
+    /// Formula and R², like `y = 2.5 x + 1.3, R² = 0.98`
+    pub fn describe(&self) -> String
+    {
+        let formula = match self.fit
+        {
+            Fit::Linear | Fit::Poly(_) =>
+            {
+                let mut terms = String::new();
+                for (power, &c) in self.coefficients.iter().enumerate().rev()
+                {
+                    let sign = if c < 0.0 { "-" } else { "+" };
+                    let term = match power
+                    {
+                        0 => coefficient(c.abs()),
+                        1 => format!("{} x", coefficient(c.abs())),
+                        _ => format!("{} x^{}", coefficient(c.abs()), power),
+                    };
+                    if terms.is_empty()
+                    {
+                        terms = if c < 0.0 { format!("-{}", term) } else { term };
+                    }
+                    else
+                    {
+                        terms = format!("{} {} {}", terms, sign, term);
+                    }
+                }
+                format!("y = {}", terms)
+            }
+            Fit::Exp => format!(
+                "y = {} e^({} x)",
+                coefficient(self.coefficients[0]),
+                coefficient(self.coefficients[1])
+            ),
+            Fit::Loess => format!("loess, span {}", LOESS_SPAN),
+        };
+        format!("{}, R² = {:.4}", formula, self.r_squared)
+    }
+}
+
+/// Four significant digits, scientific for very large or small values
+fn coefficient(value: f64) -> String
+{
+    let abs_value = value.abs();
+    if abs_value == 0.0
+    {
+        "0".to_string()
+    }
+    else if (1e-3..1e6).contains(&abs_value)
+    {
+        let decimals = (3 - abs_value.log10().floor() as i32).max(0) as usize;
+        let formatted = format!("{:.*}", decimals, value);
+        if formatted.contains('.')
+        {
+            formatted
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string()
+        }
+        else
+        {
+            formatted
+        }
+    }
+    else
+    {
+        format!("{:.3e}", value)
+    }
+}
+
+/// Fits the finite points, None if there are too few of them for `fit`, an error if the
+/// polynomial is numerically unstable for them.
+///
+/// On a logarithmic X axis the curve is sampled evenly in log space.
+pub fn fit(points: &[(f64, f64)], fit: Fit, log_x: bool) -> Result<Option<Fitted>, PlotError>
+{
+    let points: Vec<(f64, f64)> = points
+        .iter()
+        .copied()
+        .filter(|(x, y)| x.is_finite() && y.is_finite() && (!log_x || *x > 0.0))
+        .collect();
+    let (x_min, x_max) = points
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), &(x, _)| (a.min(x), b.max(x)));
+    if points.len() < 2 || x_min == x_max
+    {
+        return Ok(None);
+    }
+    let xs: Vec<f64> = (0..SAMPLES)
+        .map(|i| {
+            let t = i as f64 / (SAMPLES - 1) as f64;
+            if log_x
+            {
+                (x_min.ln() + t * (x_max.ln() - x_min.ln())).exp()
+            }
+            else
+            {
+                x_min + t * (x_max - x_min)
+            }
+        })
+        .collect();
+
+    let (curve, coefficients, predict): (Vec<(f64, f64)>, Vec<f64>, Predict) = match fit
+    {
+        Fit::Linear | Fit::Poly(_) =>
+        {
+            let degree = if let Fit::Poly(degree) = fit
+            {
+                degree
+            }
+            else
+            {
+                1
+            };
+            let Some(coefficients) = polynomial(&points, degree)?
+            else
+            {
+                return Ok(None);
+            };
+            let evaluate = {
+                let coefficients = coefficients.clone();
+                move |x: f64| coefficients.iter().rev().fold(0.0, |sum, c| sum * x + c)
+            };
+            let curve = xs.iter().map(|&x| (x, evaluate(x))).collect();
+            (curve, coefficients, Box::new(evaluate))
+        }
+        Fit::Exp =>
+        {
+            let logs: Vec<(f64, f64)> = points
+                .iter()
+                .filter(|(_, y)| *y > 0.0)
+                .map(|&(x, y)| (x, y.ln()))
+                .collect();
+            let Some(line) = polynomial(&logs, 1)?
+            else
+            {
+                return Ok(None);
+            };
+            let (a, b) = (line[0].exp(), line[1]);
+            let evaluate = move |x: f64| a * (b * x).exp();
+            let curve = xs.iter().map(|&x| (x, evaluate(x))).collect();
+            (curve, vec![a, b], Box::new(evaluate))
+        }
+        Fit::Loess =>
+        {
+            let mut sorted = points.clone();
+            sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let curve: Vec<(f64, f64)> = xs.iter().map(|&x| (x, loess_at(&sorted, x))).collect();
+            // the data is compared against the curve between its samples
+            let sampled = curve.clone();
+            let evaluate = move |x: f64| interpolate(&sampled, x);
+            (curve, Vec::new(), Box::new(evaluate))
+        }
+    };
+
+    let mean = points.iter().map(|(_, y)| y).sum::<f64>() / points.len() as f64;
+    let total: f64 = points.iter().map(|(_, y)| (y - mean).powi(2)).sum();
+    let residual: f64 = points.iter().map(|&(x, y)| (y - predict(x)).powi(2)).sum();
+    let r_squared = if total > 0.0
+    {
+        1.0 - residual / total
+    }
+    else
+    {
+        1.0
+    };
+
+    Ok(Some(Fitted {
+        curve: curve.into_iter().filter(|(_, y)| y.is_finite()).collect(),
+        coefficients,
+        r_squared,
+        fit,
+    }))
+}
+
+/// Least squares polynomial coefficients from the constant up, None if underdetermined.
+///
+/// X is centered and scaled for a well conditioned system, the coefficients are for raw X.
+fn polynomial(points: &[(f64, f64)], degree: usize) -> Result<Option<Vec<f64>>, PlotError>
+{
+    let n = points.len() as f64;
+    let center = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let scale = (points
+        .iter()
+        .map(|(x, _)| (x - center).powi(2))
+        .sum::<f64>()
+        / n)
+        .sqrt();
+    let mut distinct: Vec<f64> = points.iter().map(|(x, _)| *x).collect();
+    distinct.sort_by(f64::total_cmp);
+    distinct.dedup();
+    if distinct.len() <= degree || scale == 0.0
+    {
+        return Ok(None);
+    }
+
+    // normal equations in t = (x - center) / scale
+    let size = degree + 1;
+    let mut matrix = vec![vec![0.0; size + 1]; size];
+    for &(x, y) in points
+    {
+        let t = (x - center) / scale;
+        let powers: Vec<f64> = (0..2 * size).map(|k| t.powi(k as i32)).collect();
+        for (row, values) in matrix.iter_mut().enumerate()
+        {
+            for (column, value) in values.iter_mut().take(size).enumerate()
+            {
+                *value += powers[row + column];
+            }
+            values[size] += powers[row] * y;
+        }
+    }
+    let Some(scaled) = solve(matrix)
+    else
+    {
+        return Err(PlotError::InvalidData(format!(
+            "Fitting a polynomial of degree {} is numerically unstable for this data",
+            degree
+        )));
+    };
+
+    // expand sum a_k ((x - center) / scale)^k into powers of x
+    let mut coefficients = vec![0.0; size];
+    for (k, a) in scaled.iter().enumerate()
+    {
+        let factor = a / scale.powi(k as i32);
+        let mut binomial = 1.0;
+        for (j, coefficient) in coefficients.iter_mut().enumerate().take(k + 1)
+        {
+            *coefficient += factor * binomial * (-center).powi((k - j) as i32);
+            binomial = binomial * (k - j) as f64 / (j + 1) as f64;
+        }
+    }
+    Ok(Some(coefficients))
+}
+
+/// Gaussian elimination with partial pivoting on an augmented matrix, None if singular
+fn solve(mut matrix: Vec<Vec<f64>>) -> Option<Vec<f64>>
+{
+    let size = matrix.len();
+    for i in 0..size
+    {
+        let pivot = (i..size).max_by(|&a, &b| matrix[a][i].abs().total_cmp(&matrix[b][i].abs()))?;
+        if matrix[pivot][i].abs() < 1e-12
+        {
+            return None;
+        }
+        matrix.swap(i, pivot);
+        let (upper, lower) = matrix.split_at_mut(i + 1);
+        let pivot_row = &upper[i];
+        for row in lower
+        {
+            let factor = row[i] / pivot_row[i];
+            for (value, pivot) in row[i..].iter_mut().zip(&pivot_row[i..])
+            {
+                *value -= factor * pivot;
+            }
+        }
+    }
+    let mut solution = vec![0.0; size];
+    for i in (0..size).rev()
+    {
+        let sum: f64 = (i + 1..size).map(|j| matrix[i][j] * solution[j]).sum();
+        solution[i] = (matrix[i][size] - sum) / matrix[i][i];
+    }
+    Some(solution)
+}
+
+/// Tricube weighted line through the nearest points around `x`, `sorted` by X
+fn loess_at(sorted: &[(f64, f64)], x: f64) -> f64
+{
+    let count = ((sorted.len() as f64 * LOESS_SPAN).ceil() as usize).clamp(2, sorted.len());
+    // grow a window of the nearest points from the insertion position
+    let mut low = sorted.partition_point(|(px, _)| *px < x);
+    let mut high = low;
+    while high - low < count
+    {
+        let take_low = match (low.checked_sub(1), sorted.get(high))
+        {
+            (Some(left), Some(right)) => x - sorted[left].0 <= right.0 - x,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        if take_low
+        {
+            low -= 1;
+        }
+        else
+        {
+            high += 1;
+        }
+    }
+    let window = &sorted[low..high];
+    let reach = window
+        .iter()
+        .map(|(px, _)| (px - x).abs())
+        .fold(0.0, f64::max)
+        * 1.000001;
+
+    let (mut w_sum, mut wx, mut wy, mut wxx, mut wxy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for &(px, py) in window
+    {
+        let d = if reach > 0.0
+        {
+            (px - x).abs() / reach
+        }
+        else
+        {
+            0.0
+        };
+        let w = (1.0 - d.powi(3)).powi(3);
+        w_sum += w;
+        wx += w * px;
+        wy += w * py;
+        wxx += w * px * px;
+        wxy += w * px * py;
+    }
+    let mean_x = wx / w_sum;
+    let mean_y = wy / w_sum;
+    let spread = wxx / w_sum - mean_x * mean_x;
+    if spread <= f64::EPSILON * mean_x.abs().max(1.0)
+    {
+        return mean_y;
+    }
+    let slope = (wxy / w_sum - mean_x * mean_y) / spread;
+    mean_y + slope * (x - mean_x)
+}
+
+/// Linear interpolation between the samples of a curve ascending in X
+fn interpolate(curve: &[(f64, f64)], x: f64) -> f64
+{
+    let i = curve
+        .partition_point(|(cx, _)| *cx < x)
+        .clamp(1, curve.len() - 1);
+    let ((x0, y0), (x1, y1)) = (curve[i - 1], curve[i]);
+    if x1 == x0
+    {
+        y0
+    }
+    else
+    {
+        y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool
+    {
+        (a - b).abs() < 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn quadratic_recovers_coefficients()
+    {
+        let points: Vec<(f64, f64)> = (-3..=4)
+            .map(f64::from)
+            .map(|x| (x, 2.0 * x * x - 3.0))
+            .collect();
+        let fitted = fit(&points, Fit::Poly(2), false).unwrap().unwrap();
+        for (c, expected) in fitted.coefficients.iter().zip([-3.0, 0.0, 2.0])
+        {
+            assert!(close(*c, expected), "{:?}", fitted.coefficients);
+        }
+        assert!(close(fitted.r_squared, 1.0));
+        assert_eq!(fitted.curve.len(), SAMPLES);
+    }
+
+    #[test]
+    fn linear_on_far_off_x()
+    {
+        // centering keeps the normal equations well conditioned
+        let points: Vec<(f64, f64)> = (0..10)
+            .map(|i| 1e6 + i as f64)
+            .map(|x| (x, 0.5 * x + 7.0))
+            .collect();
+        let fitted = fit(&points, Fit::Linear, false).unwrap().unwrap();
+        assert!((fitted.coefficients[1] - 0.5).abs() < 1e-9, "{:?}", fitted.coefficients);
+        assert!((fitted.coefficients[0] - 7.0).abs() < 1e-3, "{:?}", fitted.coefficients);
+        assert!(close(fitted.r_squared, 1.0));
+    }
+
+    #[test]
+    fn exponential()
+    {
+        let points: Vec<(f64, f64)> = (0..8)
+            .map(f64::from)
+            .map(|x| (x, 3.0 * (0.5 * x).exp()))
+            .collect();
+        let fitted = fit(&points, Fit::Exp, false).unwrap().unwrap();
+        assert!(close(fitted.coefficients[0], 3.0) && close(fitted.coefficients[1], 0.5));
+        assert_eq!(fitted.describe(), "y = 3 e^(0.5 x), R² = 1.0000");
+    }
+
+    #[test]
+    fn loess_follows_a_line()
+    {
+        let points: Vec<(f64, f64)> = (0..20).map(f64::from).map(|x| (x, 4.0 - 1.5 * x)).collect();
+        let fitted = fit(&points, Fit::Loess, false).unwrap().unwrap();
+        assert!(fitted.curve.iter().all(|&(x, y)| close(y, 4.0 - 1.5 * x)));
+        assert!(close(fitted.r_squared, 1.0));
+    }
+
+    #[test]
+    fn r_squared_of_imperfect_fits()
+    {
+        // the best line is flat at the mean, explaining nothing
+        let points = [(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)];
+        let fitted = fit(&points, Fit::Linear, false).unwrap().unwrap();
+        assert!(close(fitted.r_squared, 0.0), "{}", fitted.r_squared);
+
+        let points = [(0.0, 1.0), (1.0, 3.0), (2.0, 2.0), (3.0, 5.0)];
+        let fitted = fit(&points, Fit::Linear, false).unwrap().unwrap();
+        // slope 1.1, intercept 1.1, residuals 0.01 + 0.64 + 1.69 + 0.36 over 8.75
+        assert!(close(fitted.r_squared, 1.0 - 2.7 / 8.75), "{}", fitted.r_squared);
+        assert_eq!(fitted.describe(), "y = 1.1 x + 1.1, R² = 0.6914");
+    }
+
+    #[test]
+    fn too_few_points()
+    {
+        assert!(fit(&[(1.0, 2.0)], Fit::Linear, false).unwrap().is_none());
+        assert!(fit(&[(1.0, 2.0), (1.0, 3.0)], Fit::Linear, false)
+            .unwrap()
+            .is_none());
+        assert!(fit(&[(0.0, 1.0), (1.0, 2.0)], Fit::Poly(2), false)
+            .unwrap()
+            .is_none());
+        assert!(fit(&[(0.0, 1.0), (-1.0, 2.0)], Fit::Linear, true)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn unstable_fit_is_an_error()
+    {
+        let points = [(0.0, 1.0), (1.0, 2.0), (1.0 + 1e-9, 5.0), (2.0, 3.0)];
+        assert!(fit(&points, Fit::Poly(3), false).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+//! Histograms of a single column, optionally split into color facet groups.
+
+use crate::PlotError;
+
+use polars::prelude::*;
+use std::str::FromStr;
+
+/// How the bin width is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Bins
+{
+    /// fixed number of bins over the data range
+    Count(usize),
+    /// fixed bin width, bins start at a multiple of it
+    Width(f64),
+    /// 2 IQR / n^(1/3), robust against outliers
+    #[default]
+    FreedmanDiaconis,
+    /// log2(n) + 1 bins
+    Sturges,
+}
+
+impl FromStr for Bins
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "fd" | "freedman-diaconis" => Ok(Bins::FreedmanDiaconis),
+            "sturges" => Ok(Bins::Sturges),
+            _ => match s.parse::<usize>()
+            {
+                Ok(count) if count > 0 => Ok(Bins::Count(count)),
+                _ => Err(format!("unknown bins {}, expected a positive count, fd or sturges", s)),
+            },
+        }
+    }
+}
+
+/// Binning and display of a histogram
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Histogram
+{
+    pub bins: Bins,
+    /// logarithmically spaced bins over the positive values, a bin width is given in decades
+    pub log: bool,
+    /// normalize each group to an area of 1 instead of counting
+    pub density: bool,
+    /// stack the groups of a color facet instead of overlaying them
+    pub stack: bool,
+}
+
+/// Bin edges and the value of each bin per group
+pub struct Binned
+{
+    /// one more edge than bins, in data units
+    pub edges: Vec<f64>,
+    pub values: Vec<Vec<f64>>,
+}
+
+/// Bins `series` for each of `n_groups` groups, `groups` gives the group of each row.
+///
+/// Missing values and, for log bins, values not above zero are left out.
+pub fn bin(
+    series: &Series,
+    groups: Option<&[usize]>,
+    n_groups: usize,
+    hist: &Histogram,
+) -> Result<Binned, PlotError>
+{
+    let values = series.cast(&DataType::Float64)?;
+    let values = values
+        .f64()
+        .map_err(|_| PlotError::InvalidData("Histogram column is not numeric".to_string()))?;
+    let scale = |v: f64| {
+        if !hist.log
+        {
+            Some(v)
+        }
+        else if v > 0.0
+        {
+            Some(v.log10())
+        }
+        else
+        {
+            None
+        }
+    };
+    let scaled: Float64Chunked = values
+        .into_iter()
+        .map(|v| v.and_then(scale).filter(|v| v.is_finite()))
+        .collect();
+
+    let mut sorted: Vec<f64> = scaled.into_iter().flatten().collect();
+    if sorted.is_empty()
+    {
+        return Err(PlotError::InvalidData("No values to bin in histogram column".to_string()));
+    }
+    sorted.sort_by(f64::total_cmp);
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    let n = sorted.len() as f64;
+    let range = max - min;
+
+    let sturges_width = range / (n.log2().ceil() + 1.0);
+    let (start, width) = match hist.bins
+    {
+        Bins::Count(count) => (min, range / count as f64),
+        Bins::Width(width) if width > 0.0 => ((min / width).floor() * width, width),
+        Bins::Width(width) =>
+        {
+            return Err(PlotError::InvalidData(format!(
+                "Bin width must be positive, got {}",
+                width
+            )))
+        }
+        Bins::Sturges => (min, sturges_width),
+        Bins::FreedmanDiaconis =>
+        {
+            let quantile = |q: f64| sorted[((n - 1.0) * q).round() as usize];
+            let iqr = quantile(0.75) - quantile(0.25);
+            // no spread in the middle half, e.g. mostly constant data
+            let width = if iqr > 0.0
+            {
+                2.0 * iqr / n.cbrt()
+            }
+            else
+            {
+                sturges_width
+            };
+            (min, width)
+        }
+    };
+    // all values equal
+    let width = if width > 0.0 { width } else { 1.0 };
+    // the maximum closes the last bin instead of opening one more
+    let n_bins = (((max - start) / width - 1e-9).ceil() as usize).max(1);
+    if n_bins > 100_000
+    {
+        return Err(PlotError::InvalidData(format!(
+            "Bin width {} gives {} bins, use a larger one",
+            width, n_bins
+        )));
+    }
+    let last_bin = n_bins as i64 - 1;
+
+    let group: UInt32Chunked = match groups
+    {
+        Some(groups) => groups.iter().map(|&g| Some(g as u32)).collect(),
+        None => (0..scaled.len()).map(|_| Some(0u32)).collect(),
+    };
+    let frame = DataFrame::new(vec![
+        scaled.with_name("value".into()).into_series().into(),
+        group.with_name("group".into()).into_series().into(),
+    ])?;
+    let bin_index = ((col("value") - lit(start)) / lit(width)).cast(DataType::Int64);
+    let counts = frame
+        .lazy()
+        .filter(col("value").is_not_null())
+        .with_column(
+            when(bin_index.clone().gt(lit(last_bin)))
+                .then(lit(last_bin))
+                .otherwise(bin_index)
+                .alias("bin"),
+        )
+        .group_by([col("group"), col("bin")])
+        .agg([len().alias("count")])
+        .collect()?;
+
+    let mut binned_values = vec![vec![0.0; n_bins]; n_groups.max(1)];
+    let count_groups = counts.column("group")?.u32()?;
+    let count_bins = counts.column("bin")?.i64()?;
+    let count_values = counts.column("count")?.cast(&DataType::Float64)?;
+    for ((g, b), c) in count_groups
+        .into_iter()
+        .zip(count_bins)
+        .zip(count_values.f64()?)
+    {
+        if let (Some(g), Some(b), Some(c)) = (g, b, c)
+        {
+            if let Some(group) = binned_values.get_mut(g as usize)
+            {
+                group[b.clamp(0, last_bin) as usize] += c;
+            }
+        }
+    }
+
+    let unscale = |e: f64| if hist.log { 10f64.powf(e) } else { e };
+    let edges: Vec<f64> = (0..=n_bins)
+        .map(|i| unscale(start + i as f64 * width))
+        .collect();
+    if hist.density
+    {
+        for group in binned_values.iter_mut()
+        {
+            let total: f64 = group.iter().sum();
+            if total > 0.0
+            {
+                for (i, value) in group.iter_mut().enumerate()
+                {
+                    *value /= total * (edges[i + 1] - edges[i]);
+                }
+            }
+        }
+    }
+    Ok(Binned {
+        edges,
+        values: binned_values,
+    })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn binned(values: &[f64], hist: &Histogram) -> Binned
+    {
+        bin(&Series::new("v".into(), values), None, 1, hist).unwrap()
+    }
+
+    #[test]
+    fn sturges_bins()
+    {
+        let values: Vec<f64> = (1..=8).map(f64::from).collect();
+        let hist = Histogram {
+            bins: Bins::Sturges,
+            ..Default::default()
+        };
+        // log2(8) + 1 bins of width 7 / 4
+        let binned = binned(&values, &hist);
+        assert_eq!(binned.edges, [1.0, 2.75, 4.5, 6.25, 8.0]);
+        assert_eq!(binned.values, [[2.0, 2.0, 2.0, 2.0]]);
+    }
+
+    #[test]
+    fn freedman_diaconis_bins()
+    {
+        let values: Vec<f64> = (0..100).map(f64::from).collect();
+        let binned = binned(&values, &Histogram::default());
+        // 2 * IQR / cbrt(n) with IQR 74 - 25
+        let width = 2.0 * 49.0 / 100f64.cbrt();
+        assert_eq!(binned.edges.len(), 6);
+        assert!((binned.edges[1] - width).abs() < 1e-9);
+        assert_eq!(binned.values[0].iter().sum::<f64>(), 100.0);
+    }
+
+    #[test]
+    fn log_bins()
+    {
+        let hist = Histogram {
+            bins: Bins::Count(3),
+            log: true,
+            ..Default::default()
+        };
+        // non-positive values are left out, the maximum closes the last bin
+        let binned = binned(&[-1.0, 0.0, 1.0, 10.0, 100.0, 1000.0], &hist);
+        let expected = [1.0, 10.0, 100.0, 1000.0];
+        for (edge, expected) in binned.edges.iter().zip(expected)
+        {
+            assert!((edge - expected).abs() < 1e-9 * expected, "{:?}", binned.edges);
+        }
+        assert_eq!(binned.values, [[1.0, 1.0, 2.0]]);
+    }
+
+    #[test]
+    fn density_areas_sum_to_one()
+    {
+        let values = [0.5, 1.0, 1.5, 2.0, 2.2, 7.0, 9.0, 1.0, 4.0];
+        let groups = [0, 0, 0, 0, 1, 1, 1, 1, 1];
+        for log in [false, true]
+        {
+            let hist = Histogram {
+                bins: Bins::Count(5),
+                log,
+                density: true,
+                ..Default::default()
+            };
+            let binned = bin(&Series::new("v".into(), values), Some(&groups), 2, &hist).unwrap();
+            for group in &binned.values
+            {
+                let area: f64 = group
+                    .iter()
+                    .zip(binned.edges.windows(2))
+                    .map(|(value, edge)| value * (edge[1] - edge[0]))
+                    .sum();
+                assert!((area - 1.0).abs() < 1e-9, "{}", area);
+            }
+        }
+    }
+}
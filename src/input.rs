@@ -0,0 +1,748 @@
+use crate::PlotError;
+
+use polars::prelude::*;
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Column delimiter of delimited text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delimiter
+{
+    Byte(u8),
+    /// several bytes like `::`
+    Text(String),
+    /// runs of spaces and tabs as in aligned columns
+    Whitespace,
+}
+
+/// Translates the delimiter given on the command line, a single character, an escaped `\t`,
+/// one of the aliases tab, comma, space, semicolon, pipe and whitespace, or a longer string
+pub fn parse_delimiter(delimiter: &str) -> Result<Delimiter, PlotError>
+{
+    Ok(match delimiter.to_lowercase().as_str()
+    {
+        "" => return Err(PlotError::InvalidData("Empty delimiter".to_string())),
+        r"\t" | "tab" => Delimiter::Byte(b'\t'),
+        "comma" => Delimiter::Byte(b','),
+        "space" => Delimiter::Byte(b' '),
+        "semicolon" => Delimiter::Byte(b';'),
+        "pipe" => Delimiter::Byte(b'|'),
+        "whitespace" => Delimiter::Whitespace,
+        _ => match delimiter.as_bytes()
+        {
+            [byte] => Delimiter::Byte(*byte),
+            _ => Delimiter::Text(delimiter.to_string()),
+        },
+    })
+}
+
+/// Candidates for `sniff_delimiter` in order of preference, the comma late as it is also
+/// common as decimal separator, single spaces and runs of whitespace last as they are also
+/// common within fields
+const SNIFFED_DELIMITERS: [Delimiter; 6] = [
+    Delimiter::Byte(b'\t'),
+    Delimiter::Byte(b';'),
+    Delimiter::Byte(b'|'),
+    Delimiter::Byte(b','),
+    Delimiter::Byte(b' '),
+    Delimiter::Whitespace,
+];
+const SNIFFED_LINES: usize = 20;
+
+/// Guesses the delimiter from the first lines after `skip`.
+///
+/// The tab wins if it is found on every line, or on any line with `tab_named` for a file
+/// named or declared tab separated, so that ragged rows do not make another candidate look
+/// more regular. Otherwise each candidate splits most lines into some number of fields, the
+/// one doing so on the largest share of lines wins, then the one giving more fields, then
+/// the earlier one. Without any candidate the delimiter is `fallback`.
+fn sniff_delimiter(
+    path: &Path,
+    skip: usize,
+    tab_named: bool,
+    fallback: u8,
+) -> Result<Delimiter, PlotError>
+{
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut lines = Vec::with_capacity(SNIFFED_LINES);
+    let mut line = Vec::new();
+    let mut skipped = 0;
+    while lines.len() < SNIFFED_LINES
+    {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0
+        {
+            break;
+        }
+        let content = line.trim_ascii_end();
+        if skipped < skip
+        {
+            skipped += 1;
+        }
+        else if !content.is_empty()
+        {
+            lines.push(content.to_vec());
+        }
+    }
+
+    let tabs: Vec<usize> = lines
+        .iter()
+        .map(|line| delimiter_count(line, &SNIFFED_DELIMITERS[0]))
+        .collect();
+    let tab_first = if tab_named
+    {
+        tabs.iter().any(|&count| count > 0)
+    }
+    else
+    {
+        tabs.iter().all(|&count| count > 0)
+    };
+    if tab_first && !lines.is_empty()
+    {
+        return Ok(Delimiter::Byte(b'\t'));
+    }
+
+    let mut best: Option<(usize, usize, &Delimiter)> = None;
+    for candidate in &SNIFFED_DELIMITERS
+    {
+        let mut counts: Vec<usize> = lines
+            .iter()
+            .map(|line| delimiter_count(line, candidate))
+            .collect();
+        counts.sort_unstable();
+        // the most common count, the smaller one of a tie, with the number of its lines
+        let (count, lines_with_count) = counts
+            .chunk_by(|a, b| a == b)
+            .map(|run| (run[0], run.len()))
+            .fold((0, 0), |most, run| {
+                if run.1 > most.1
+                {
+                    run
+                }
+                else
+                {
+                    most
+                }
+            });
+        if count == 0
+        {
+            continue;
+        }
+        if best.is_none_or(|(best_count, best_lines, _)| {
+            (lines_with_count, count) > (best_lines, best_count)
+        })
+        {
+            best = Some((count, lines_with_count, candidate));
+        }
+    }
+    Ok(best.map_or(Delimiter::Byte(fallback), |(_, _, delimiter)| delimiter.clone()))
+}
+
+/// Number of delimiters in a line, bytes within double quotes do not count
+fn delimiter_count(line: &[u8], delimiter: &Delimiter) -> usize
+{
+    match delimiter
+    {
+        Delimiter::Byte(delimiter) =>
+        {
+            let mut quoted = false;
+            line.iter()
+                .filter(|&&byte| {
+                    quoted ^= byte == b'"';
+                    !quoted && byte == *delimiter
+                })
+                .count()
+        }
+        _ => split_fields(line, delimiter).len().saturating_sub(1),
+    }
+}
+
+/// Splits a line into its fields at `delimiter`
+fn split_fields<'a>(line: &'a [u8], delimiter: &Delimiter) -> Vec<&'a [u8]>
+{
+    match delimiter
+    {
+        Delimiter::Byte(byte) => line.split(|b| b == byte).collect(),
+        Delimiter::Whitespace => line
+            .split(|b| *b == b' ' || *b == b'\t')
+            .filter(|field| !field.is_empty())
+            .collect(),
+        Delimiter::Text(text) =>
+        {
+            let text = text.as_bytes();
+            let mut fields = Vec::new();
+            let mut rest = line;
+            while let Some(at) = rest.windows(text.len()).position(|window| window == text)
+            {
+                fields.push(&rest[..at]);
+                rest = &rest[at + text.len()..];
+            }
+            fields.push(rest);
+            fields
+        }
+    }
+}
+
+/// Field separator of text rewritten by `resplit`, the ASCII unit separator
+const UNIT_SEPARATOR: u8 = 0x1f;
+
+/// Copies the lines after `skip` to a temporary file with their fields separated by
+/// `UNIT_SEPARATOR`, as the CSV reader only splits at single bytes
+fn resplit(path: &Path, delimiter: &Delimiter, skip: usize) -> Result<Spooled, PlotError>
+{
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let spooled = Spooled::create("split", None)?;
+    let mut writer = std::io::BufWriter::new(spooled.file.as_file());
+    let mut line = Vec::new();
+    let mut number = 0;
+    loop
+    {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0
+        {
+            break;
+        }
+        number += 1;
+        if number <= skip
+        {
+            continue;
+        }
+        let content = line.strip_suffix(b"\n").unwrap_or(&line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        writer.write_all(&split_fields(content, delimiter).join(&UNIT_SEPARATOR))?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    drop(writer);
+    Ok(spooled)
+}
+
+/// Format of an input table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat
+{
+    /// delimited text, comma separated unless a delimiter is given
+    Csv,
+    /// delimited text, tab separated unless a delimiter is given
+    Tsv,
+    Parquet,
+    /// Arrow IPC file, also known as Feather
+    Ipc,
+    /// one JSON object per line
+    Ndjson,
+    /// a JSON array of objects
+    Json,
+}
+
+impl FromStr for InputFormat
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "csv" => Ok(InputFormat::Csv),
+            "tsv" => Ok(InputFormat::Tsv),
+            "parquet" => Ok(InputFormat::Parquet),
+            "ipc" | "arrow" | "feather" => Ok(InputFormat::Ipc),
+            "ndjson" | "jsonl" => Ok(InputFormat::Ndjson),
+            "json" => Ok(InputFormat::Json),
+            _ => Err(format!(
+                "unknown input format {}, expected csv, tsv, parquet, ipc, ndjson or json",
+                s
+            )),
+        }
+    }
+}
+
+impl InputFormat
+{
+    /// Detects the format by the magic bytes of Parquet and Arrow files, then by the file
+    /// extension, then by a leading `[` or `{` of JSON. Anything else is delimited text.
+    pub fn detect(path: &Path) -> Result<InputFormat, PlotError>
+    {
+        let mut head = Vec::with_capacity(64);
+        std::fs::File::open(path)?.take(64).read_to_end(&mut head)?;
+        if head.starts_with(b"PAR1")
+        {
+            return Ok(InputFormat::Parquet);
+        }
+        if head.starts_with(b"ARROW1")
+        {
+            return Ok(InputFormat::Ipc);
+        }
+        if let Some(format) = InputFormat::from_extension(path)
+        {
+            return Ok(format);
+        }
+        Ok(match head.iter().find(|byte| !byte.is_ascii_whitespace())
+        {
+            Some(b'[') => InputFormat::Json,
+            Some(b'{') => InputFormat::Ndjson,
+            _ => InputFormat::Tsv,
+        })
+    }
+
+    /// Format named by the file extension
+    fn from_extension(path: &Path) -> Option<InputFormat>
+    {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref()
+        {
+            Some("csv") => Some(InputFormat::Csv),
+            Some("tsv" | "tab") => Some(InputFormat::Tsv),
+            Some("parquet" | "pq") => Some(InputFormat::Parquet),
+            Some("arrow" | "ipc" | "feather") => Some(InputFormat::Ipc),
+            Some("ndjson" | "jsonl") => Some(InputFormat::Ndjson),
+            Some("json") => Some(InputFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a table from `path`, or STDIN if no path is given, into a DataFrame.
+///
+/// With `references` only the columns mentioned there are parsed, e.g. the X and Y columns,
+/// filters and expressions. The other columns stay in place as nulls, so that column indices
+/// keep their meaning.
+///
+/// Files are scanned without loading them whole, STDIN is spooled to a temporary file first.
+/// Gzip, zstd and bzip2 compressed input is detected by its magic bytes and decompressed to a
+/// temporary file, the format is then detected from the file name without the compression
+/// extension, e.g. `.tsv` for `data.tsv.gz`.
+/// Without a `format` it is detected, without a `delimiter` it is sniffed from the first lines
+/// of delimited text, see `sniff_delimiter`. `has_header` and `skip` only apply to delimited text.
+pub fn read_columns(
+    path: Option<&Path>,
+    format: Option<InputFormat>,
+    delimiter: Option<Delimiter>,
+    has_header: bool,
+    skip: usize,
+    references: Option<&[&str]>,
+) -> Result<DataFrame, PlotError>
+{
+    let spooled;
+    let path = match path
+    {
+        Some(path) => path,
+        None =>
+        {
+            spooled = Spooled::new(std::io::stdin().lock(), "stdin", None)?;
+            spooled.path()
+        }
+    };
+    let decompressed;
+    let path = match Compression::detect(path)?
+    {
+        Some(compression) =>
+        {
+            decompressed = compression.decompress(path)?;
+            decompressed.path()
+        }
+        None => path,
+    };
+
+    // a tab separated format or file name ranks the tab first when sniffing
+    let tab_named = format.or_else(|| InputFormat::from_extension(path)) == Some(InputFormat::Tsv);
+    let format = match format
+    {
+        Some(format) => format,
+        None => InputFormat::detect(path)?,
+    };
+    let split;
+    let mut table = match format
+    {
+        InputFormat::Csv | InputFormat::Tsv =>
+        {
+            let fallback = if format == InputFormat::Csv
+            {
+                b','
+            }
+            else
+            {
+                b'\t'
+            };
+            let delimiter = match delimiter
+            {
+                Some(delimiter) => delimiter,
+                None => sniff_delimiter(path, skip, tab_named, fallback)?,
+            };
+            let (path, separator, skip) = match delimiter
+            {
+                Delimiter::Byte(byte) => (path, byte, skip),
+                _ =>
+                {
+                    split = resplit(path, &delimiter, skip)?;
+                    (split.path(), UNIT_SEPARATOR, 0)
+                }
+            };
+            LazyCsvReader::new(path)
+                .with_glob(false)
+                .with_separator(separator)
+                .with_try_parse_dates(false)
+                .with_missing_is_null(true)
+                .with_truncate_ragged_lines(true)
+                .with_ignore_errors(true)
+                .with_skip_rows(skip)
+                .with_has_header(has_header)
+                .finish()?
+        }
+        InputFormat::Parquet => LazyFrame::scan_parquet(path, ScanArgsParquet::default())?,
+        InputFormat::Ipc => LazyFrame::scan_ipc(path, ScanArgsIpc::default())?,
+        InputFormat::Ndjson => LazyJsonLineReader::new(path).finish()?,
+        // a JSON array cannot be scanned in parts
+        InputFormat::Json => JsonReader::new(std::fs::File::open(path)?).finish()?.lazy(),
+    };
+    if let Some(references) = references
+    {
+        let names: Vec<PlSmallStr> = table.collect_schema()?.iter_names().cloned().collect();
+        let used = used_columns(&names, references);
+        let columns: Vec<Expr> = names
+            .iter()
+            .zip(used)
+            .map(|(name, used)| {
+                if used
+                {
+                    col(name.clone())
+                }
+                else
+                {
+                    lit(NULL).alias(name.clone())
+                }
+            })
+            .collect();
+        table = table.select(columns);
+    }
+    Ok(table.collect()?)
+}
+
+/// Marks the columns that `references` may resolve to, by header name, by 1-based index or
+/// range like `2-5`, and by the names and `$N` or `colN` indices in expressions.
+///
+/// Marks rather too many than too few, an unmarked column reads as nulls.
+fn used_columns(names: &[PlSmallStr], references: &[&str]) -> Vec<bool>
+{
+    let mut used = vec![false; names.len()];
+    let mut mark_index = |index: usize| {
+        if let Some(slot) = index.checked_sub(1).and_then(|i| used.get_mut(i))
+        {
+            *slot = true;
+        }
+    };
+    let mut words: Vec<String> = Vec::new();
+    for reference in references
+    {
+        words.extend(crate::expr::column_tokens(reference));
+        for part in reference.split(',').map(str::trim)
+        {
+            words.push(part.to_string());
+            words.extend(crate::expr::column_tokens(part));
+            if let Some((first, last)) = part.split_once('-')
+            {
+                if let (Ok(first), Ok(last)) =
+                    (first.trim().parse::<usize>(), last.trim().parse::<usize>())
+                {
+                    (first..=last.min(names.len())).for_each(&mut mark_index);
+                }
+            }
+        }
+    }
+    for word in &words
+    {
+        if let Ok(index) = word.parse::<usize>()
+        {
+            mark_index(index);
+        }
+        if let Some(Ok(index)) = word.strip_prefix("col").map(str::parse::<usize>)
+        {
+            mark_index(index);
+        }
+    }
+    names
+        .iter()
+        .zip(used)
+        .map(|(name, used)| used || words.iter().any(|word| word == name.as_str()))
+        .collect()
+}
+
+/// Compression of an input file
+#[derive(Debug, Clone, Copy)]
+enum Compression
+{
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression
+{
+    /// Detects the compression by the magic bytes at the start of the file
+    fn detect(path: &Path) -> Result<Option<Compression>, PlotError>
+    {
+        let mut head = Vec::with_capacity(4);
+        std::fs::File::open(path)?.take(4).read_to_end(&mut head)?;
+        Ok(
+            if head.starts_with(&[0x1f, 0x8b])
+            {
+                Some(Compression::Gzip)
+            }
+            else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd])
+            {
+                Some(Compression::Zstd)
+            }
+            else if head.starts_with(b"BZh")
+            {
+                Some(Compression::Bzip2)
+            }
+            else
+            {
+                None
+            },
+        )
+    }
+
+    /// Decompresses `path` to a temporary file that keeps the extension under the compression one
+    fn decompress(self, path: &Path) -> Result<Spooled, PlotError>
+    {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let extension = path
+            .file_stem()
+            .map(Path::new)
+            .and_then(Path::extension)
+            .and_then(|extension| extension.to_str());
+        // concatenated members as written by bgzip or pbzip2 are read as one stream
+        match self
+        {
+            Compression::Gzip =>
+            {
+                Spooled::new(flate2::bufread::MultiGzDecoder::new(file), "input", extension)
+            }
+            Compression::Zstd =>
+            {
+                Spooled::new(zstd::Decoder::with_buffer(file)?, "input", extension)
+            }
+            Compression::Bzip2 =>
+            {
+                Spooled::new(bzip2::bufread::MultiBzDecoder::new(file), "input", extension)
+            }
+        }
+    }
+}
+
+/// Input copied to a temporary file in fixed size chunks, removed again when dropped.
+///
+/// The file gets a random name and is created exclusively, so nobody can plant a link
+/// under its name in a shared temporary directory.
+struct Spooled
+{
+    file: tempfile::NamedTempFile,
+}
+
+impl Spooled
+{
+    /// Copies `source` to a temporary file named by `tag` with an optional `extension`
+    fn new(mut source: impl Read, tag: &str, extension: Option<&str>)
+        -> Result<Spooled, PlotError>
+    {
+        let mut spooled = Spooled::create(tag, extension)?;
+        std::io::copy(&mut source, spooled.file.as_file_mut())?;
+        Ok(spooled)
+    }
+
+    /// Creates the empty temporary file to be written by the caller
+    fn create(tag: &str, extension: Option<&str>) -> Result<Spooled, PlotError>
+    {
+        let suffix = extension
+            .map(|extension| format!(".{}", extension))
+            .unwrap_or_default();
+        let file = tempfile::Builder::new()
+            .prefix(&format!("plotxy-{}-", tag))
+            .suffix(&suffix)
+            .tempfile()?;
+        Ok(Spooled { file })
+    }
+
+    fn path(&self) -> &Path
+    {
+        self.file.path()
+    }
+}
+
+/// Default output file name: the input file name with `suffix` appended, in the current directory
+pub fn default_outfile(input: &Path, suffix: &str) -> Result<PathBuf, PlotError>
+{
+    let mut outname = PathBuf::new();
+    outname.set_file_name(format!(
+        "{}{}",
+        input
+            .file_name()
+            .ok_or_else(|| PlotError::InvalidData("Invalid input filename".to_string()))?
+            .to_string_lossy(),
+        suffix
+    ));
+    Ok(outname)
+}
+
+/// Default output file name for several inputs: their file names joined by `+` with `suffix`
+/// appended, in the current directory. A single input gives the same name as `default_outfile`.
+pub fn combined_outfile(inputs: &[PathBuf], suffix: &str) -> Result<PathBuf, PlotError>
+{
+    let names = inputs
+        .iter()
+        .map(|input| {
+            input
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .ok_or_else(|| PlotError::InvalidData("Invalid input filename".to_string()))
+        })
+        .collect::<Result<Vec<_>, PlotError>>()?;
+    let mut outname = PathBuf::new();
+    outname.set_file_name(format!("{}{}", names.join("+"), suffix));
+    Ok(outname)
+}
+
+/// Stacks tables with the same columns, a new column named like `label_column` tells which
+/// table each row came from. Returns the stacked table and the name of the new column,
+/// `label_column` with underscores appended if a table already has a column of that name.
+pub fn stack_labelled(
+    tables: Vec<(String, DataFrame)>,
+    label_column: &str,
+) -> Result<(DataFrame, String), PlotError>
+{
+    let first_names = match tables.first()
+    {
+        Some((_, first)) => first.get_column_names_owned(),
+        None => return Err(PlotError::InvalidData("No input tables".to_string())),
+    };
+    for (label, table) in &tables
+    {
+        if table.get_column_names_owned() != first_names
+        {
+            return Err(PlotError::InvalidData(format!(
+                "Columns of {} differ from those of {}: {} vs {}",
+                label,
+                tables[0].0,
+                table
+                    .get_column_names()
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                first_names
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+    }
+
+    let mut name = label_column.to_string();
+    while first_names.iter().any(|column| column.as_str() == name)
+    {
+        name.push('_');
+    }
+    let frames = tables
+        .into_iter()
+        .map(|(label, table)| table.lazy().with_column(lit(label).alias(name.as_str())))
+        .collect::<Vec<_>>();
+    // e.g. a column of integers in one file and floats in another
+    let args = UnionArgs {
+        to_supertypes: true,
+        ..Default::default()
+    };
+    Ok((concat(frames, args)?.collect()?, name))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Delimiter sniffed from a temporary file with `content`
+    fn sniff(content: &str, skip: usize) -> Delimiter
+    {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        sniff_delimiter(file.path(), skip, false, b'\t').unwrap()
+    }
+
+    #[test]
+    fn sniffed_delimiters()
+    {
+        assert_eq!(sniff("x,y\n1,2\n3,4\n", 0), Delimiter::Byte(b','));
+        assert_eq!(sniff("x;y;z\n1,5;2;3\n4;5,25;6\n", 0), Delimiter::Byte(b';'));
+        assert_eq!(sniff("x\ty\n1,5\t2\n3\t4,5\n", 0), Delimiter::Byte(b'\t'));
+        assert_eq!(sniff("x|y\r\n1|2\r\n", 0), Delimiter::Byte(b'|'));
+        assert_eq!(sniff("x y\n1 2\n\n3 4\n", 0), Delimiter::Byte(b' '));
+        assert_eq!(sniff("  x     y\n  1     2\n 10    20\n", 0), Delimiter::Whitespace);
+        // separators in quoted fields do not count
+        assert_eq!(
+            sniff("name,value\n\"a, b\",1\n\"c\",2\n\"d,e,f\",3\n", 0),
+            Delimiter::Byte(b',')
+        );
+        assert_eq!(sniff("name\tvalue\n\"a\tb\"\t1\nc\t2\n", 0), Delimiter::Byte(b'\t'));
+        // lines before the header are skipped
+        assert_eq!(sniff("# run 1; 2\nx,y\n1,2\n", 1), Delimiter::Byte(b','));
+        // nothing consistent
+        assert_eq!(sniff("x\n1\n", 0), Delimiter::Byte(b'\t'));
+        assert_eq!(sniff("", 0), Delimiter::Byte(b'\t'));
+        // the most regular candidate wins, not the first one found in every line
+        assert_eq!(sniff("a;b,c;d\n1;2,5;3\n4;5;6,1\n7;8\n", 0), Delimiter::Byte(b';'));
+        assert_eq!(sniff("a,b|c\n1,2|3\n4,5|6,7\n", 0), Delimiter::Byte(b'|'));
+        // a one column file with an occasional comma
+        assert_eq!(sniff("name\nfoo, bar\nbaz\nqux\n", 0), Delimiter::Byte(b'\t'));
+    }
+
+    #[test]
+    fn ragged_tsv()
+    {
+        let ragged = "id,name\tvalue\tnote\na,1\t2\tx\nb,2\t3\n";
+        assert_eq!(sniff(ragged, 0), Delimiter::Byte(b'\t'));
+
+        // a tab separated file name keeps the tab even if some lines lack it
+        let mut file = tempfile::Builder::new().suffix(".tsv").tempfile().unwrap();
+        file.write_all(b"id,name\tvalue\na,1\t2\nb,2\nc,3\n")
+            .unwrap();
+        let df = read_columns(Some(file.path()), None, None, true, 0, None).unwrap();
+        assert_eq!(df.get_column_names_str(), ["id,name", "value"]);
+        assert_eq!(df.column("value").unwrap().i64().unwrap().get(0), Some(2));
+        assert_eq!(df.height(), 3);
+    }
+
+    #[test]
+    fn delimiter_aliases()
+    {
+        assert_eq!(parse_delimiter("TAB").unwrap(), Delimiter::Byte(b'\t'));
+        assert_eq!(parse_delimiter(r"\t").unwrap(), Delimiter::Byte(b'\t'));
+        assert_eq!(parse_delimiter("pipe").unwrap(), Delimiter::Byte(b'|'));
+        assert_eq!(parse_delimiter("whitespace").unwrap(), Delimiter::Whitespace);
+        assert_eq!(parse_delimiter(";").unwrap(), Delimiter::Byte(b';'));
+        assert_eq!(parse_delimiter("::").unwrap(), Delimiter::Text("::".to_string()));
+        assert!(parse_delimiter("").is_err());
+    }
+
+    #[test]
+    fn multi_byte_delimiters()
+    {
+        let fields = |line: &str, delimiter| -> Vec<String> {
+            split_fields(line.as_bytes(), &delimiter)
+                .iter()
+                .map(|field| String::from_utf8_lossy(field).to_string())
+                .collect()
+        };
+        assert_eq!(fields("a::b::::c", Delimiter::Text("::".to_string())), ["a", "b", "", "c"]);
+        assert_eq!(fields(" a \t b  c ", Delimiter::Whitespace), ["a", "b", "c"]);
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"skipped\nx::y\n1::2\n3::4\n").unwrap();
+        let delimiter = Some(Delimiter::Text("::".to_string()));
+        let df = read_columns(Some(file.path()), None, delimiter, true, 1, None).unwrap();
+        assert_eq!(df.get_column_names_str(), ["x", "y"]);
+        assert_eq!(df.column("y").unwrap().i64().unwrap().get(1), Some(4));
+    }
+}
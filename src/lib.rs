@@ -0,0 +1,153 @@
+//! Plot tabular data from polars DataFrames.
+//!
+//! This is the library behind the `plotxy` and `plotrange` command line tools.
+//! Describe the chart with a [`PlotSpec`] and render it with [`plot_to_file`]
+//! or onto any plotters backend with [`plot_on_backend`].
+
+pub mod aggregate;
+pub mod axis;
+pub mod category;
+pub mod color;
+pub mod density;
+mod error;
+pub mod expr;
+pub mod fit;
+pub mod hist;
+pub mod input;
+pub mod panel;
+mod plot;
+mod spec;
+pub mod time;
+
+// compiles the examples in the README
+#[cfg(doctest)]
+#[doc = include_str!("../README.md")]
+struct ReadmeDoctests;
+
+pub use error::PlotError;
+pub use plot::{plot_on_backend, plot_to_file, OutputFormat, PlotReport};
+pub use spec::{ErrorColumns, LegendPosition, PlotSpec, Shape};
+
+use polars::prelude::*;
+
+/// Looks up a 1-based column index, `role` names the column in error messages
+pub fn column<'a>(df: &'a DataFrame, index: usize, role: &str) -> Result<&'a Series, PlotError>
+{
+    index
+        .checked_sub(1)
+        .and_then(|i| df.get_columns().get(i))
+        .ok_or_else(|| not_found(df, role, &index.to_string()))?
+        .as_series()
+        .ok_or_else(|| PlotError::InvalidColumn(format!("{} column conversion failed", role)))
+}
+
+/// Resolves a header name or a 1-based index to the column index, a matching name wins
+pub fn column_index(df: &DataFrame, reference: &str, role: &str) -> Result<usize, PlotError>
+{
+    if let Some(i) = df
+        .get_column_names()
+        .iter()
+        .position(|name| name.as_str() == reference)
+    {
+        return Ok(i + 1);
+    }
+    match reference.parse::<usize>()
+    {
+        Ok(index) if index <= df.width() => Ok(index),
+        _ => Err(not_found(df, role, reference)),
+    }
+}
+
+/// Resolves a list of columns like `2,3,4`, `2-5` or `latency,throughput`
+pub fn column_list(df: &DataFrame, list: &str, role: &str) -> Result<Vec<usize>, PlotError>
+{
+    let mut columns = Vec::new();
+    for part in list.split(',').map(str::trim)
+    {
+        let range = part.split_once('-').and_then(|(first, last)| {
+            Some((first.trim().parse::<usize>().ok()?, last.trim().parse::<usize>().ok()?))
+        });
+        match range
+        {
+            // a header name like a-b takes precedence over a range
+            Some((first, last)) if column_index(df, part, role).is_err() =>
+            {
+                if first > last || last > df.width()
+                {
+                    return Err(PlotError::InvalidColumn(format!(
+                        "{} is not a column range within 1-{}",
+                        part,
+                        df.width()
+                    )));
+                }
+                columns.extend(first..=last);
+            }
+            _ => columns.push(column_index(df, part, role)?),
+        }
+    }
+    Ok(columns)
+}
+
+fn not_found(df: &DataFrame, role: &str, reference: &str) -> PlotError
+{
+    let names: Vec<&str> = df
+        .get_column_names()
+        .iter()
+        .map(|name| name.as_str())
+        .collect();
+    PlotError::InvalidColumn(format!(
+        "{} column {} not found, available columns: {}",
+        role,
+        reference,
+        names.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn table() -> DataFrame
+    {
+        df!("x" => [1, 2], "y" => [3.0, 4.0], "2" => ["a", "b"], "a-b" => [5, 6]).unwrap()
+    }
+
+    #[test]
+    fn column_by_index()
+    {
+        let df = table();
+        assert_eq!(column(&df, 2, "Y").unwrap().name().as_str(), "y");
+        assert!(column(&df, 0, "Y").is_err());
+        let error = column(&df, 5, "Y").unwrap_err().to_string();
+        assert!(error.contains("Y column 5 not found"), "{}", error);
+        assert!(error.contains("x, y, 2, a-b"), "{}", error);
+    }
+
+    #[test]
+    fn column_index_by_name_or_number()
+    {
+        let df = table();
+        assert_eq!(column_index(&df, "y", "Y").unwrap(), 2);
+        assert_eq!(column_index(&df, "4", "Y").unwrap(), 4);
+        // a header name wins over the index
+        assert_eq!(column_index(&df, "2", "Y").unwrap(), 3);
+        // 0 is the row number
+        assert_eq!(column_index(&df, "0", "X").unwrap(), 0);
+        assert!(column_index(&df, "5", "Y").is_err());
+        assert!(column_index(&df, "z", "Y").is_err());
+    }
+
+    #[test]
+    fn column_list_with_ranges()
+    {
+        let df = table();
+        assert_eq!(column_list(&df, "1, y,4", "Y").unwrap(), vec![1, 2, 4]);
+        assert_eq!(column_list(&df, "1-3", "Y").unwrap(), vec![1, 2, 3]);
+        // a header name wins over the range
+        assert_eq!(column_list(&df, "a-b", "Y").unwrap(), vec![4]);
+        assert!(column_list(&df, "3-2", "Y").is_err());
+        assert!(column_list(&df, "2-5", "Y").is_err());
+        assert!(column_list(&df, "x,z", "Y").is_err());
+    }
+}
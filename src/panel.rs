@@ -0,0 +1,113 @@
+//! Small multiples, one panel per category of a facet column.
+
+use crate::color::{CategoryOrder, Facet, Palette};
+use crate::PlotError;
+
+use polars::prelude::*;
+use std::str::FromStr;
+
+/// Which axes all panels have in common
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FacetScales
+{
+    /// same X and Y range in every panel, so panels compare at a glance
+    #[default]
+    Shared,
+    /// each panel spans its own X values
+    FreeX,
+    /// each panel spans its own Y values
+    FreeY,
+    Free,
+}
+
+impl FacetScales
+{
+    pub fn shared_x(self) -> bool
+    {
+        matches!(self, FacetScales::Shared | FacetScales::FreeY)
+    }
+
+    pub fn shared_y(self) -> bool
+    {
+        matches!(self, FacetScales::Shared | FacetScales::FreeX)
+    }
+}
+
+impl FromStr for FacetScales
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "shared" => Ok(FacetScales::Shared),
+            "free-x" => Ok(FacetScales::FreeX),
+            "free-y" => Ok(FacetScales::FreeY),
+            "free" => Ok(FacetScales::Free),
+            _ =>
+            {
+                Err(format!("unknown facet scales {}, expected shared, free-x, free-y or free", s))
+            }
+        }
+    }
+}
+
+/// Panels in grid order, left to right and top to bottom
+pub struct Panels
+{
+    /// facet value of each panel, used as its title
+    pub names: Vec<String>,
+    /// rows of the DataFrame shown in each panel
+    pub masks: Vec<BooleanChunked>,
+    pub rows: usize,
+    pub columns: usize,
+}
+
+/// Splits the rows by the values of `series`, ordered like color facet categories.
+///
+/// Without `rows` and `columns` the grid is about square, with one of them the other follows.
+pub fn panels(
+    series: &Series,
+    order: CategoryOrder,
+    rows: Option<usize>,
+    columns: Option<usize>,
+) -> Result<Panels, PlotError>
+{
+    // grouped like a color facet, only its colors are not needed
+    let facet = Facet::new(series, &Palette::default(), order, &[])?;
+    let count = facet.categories.len();
+    if count == 0
+    {
+        return Err(PlotError::InvalidData("No data in facet column".to_string()));
+    }
+    let (rows, columns) = match (rows, columns)
+    {
+        (Some(0), _) | (_, Some(0)) => (0, 0),
+        (Some(rows), Some(columns)) => (rows, columns),
+        (Some(rows), None) => (rows, count.div_ceil(rows)),
+        (None, Some(columns)) => (count.div_ceil(columns), columns),
+        (None, None) =>
+        {
+            let columns = (count as f64).sqrt().ceil() as usize;
+            (count.div_ceil(columns), columns)
+        }
+    };
+    if rows * columns < count
+    {
+        return Err(PlotError::InvalidData(format!(
+            "A grid of {} rows and {} columns cannot hold {} facet panels",
+            rows, columns, count
+        )));
+    }
+
+    let masks = (0..count)
+        .map(|category| facet.indices.iter().map(|&i| i == category).collect())
+        .collect();
+    Ok(Panels {
+        names: facet.categories.into_iter().map(|(name, _)| name).collect(),
+        masks,
+        rows,
+        columns,
+    })
+}
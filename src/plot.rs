@@ -0,0 +1,2049 @@
+use crate::aggregate;
+use crate::axis::{self, format_number, format_si_number, nice_step, AxisRange};
+use crate::category::{self, CategoryAxis, XOrder};
+use crate::color::{Facet, GradientScale};
+use crate::density::{self, Cell, CellSize};
+use crate::fit::{self, Fitted};
+use crate::hist::{self, Histogram};
+use crate::panel::{self, Panels};
+use crate::spec::{ErrorColumns, LegendPosition, PlotSpec, Shape};
+use crate::time::{self, TimeAxis, TimeParse};
+use crate::{column, PlotError};
+
+use plotters::chart::{ChartBuilder, ChartContext, SeriesAnno};
+use plotters::coord::cartesian::Cartesian2d;
+use plotters::coord::ranged1d::{AsRangedCoord, BoldPoints, ValueFormatter};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+
+use polars::prelude::*;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Image format written by `plot_to_file`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat
+{
+    /// PNG and the other bitmap formats, chosen by file extension
+    Bitmap,
+    Svg,
+}
+
+/// Remarks on a rendered plot for the caller to show, the library does not print them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlotReport
+{
+    /// what was left out of the plot and why, e.g. numerically unstable fits
+    pub warnings: Vec<String>,
+}
+
+/// Renders the DataFrame as described by `spec` into the file at `path`
+pub fn plot_to_file(
+    spec: &PlotSpec,
+    df: &DataFrame,
+    path: &Path,
+    format: OutputFormat,
+) -> Result<PlotReport, PlotError>
+{
+    // every row of panels gets the full height
+    let rows = facet_panels(spec, df)?.map_or(1, |panels| panels.rows);
+    let size = (spec.width, rows as u32 * spec.height);
+
+    match format
+    {
+        OutputFormat::Svg => plot_on_backend(spec, df, SVGBackend::new(path, size)),
+        OutputFormat::Bitmap => plot_on_backend(spec, df, BitMapBackend::new(path, size)),
+    }
+}
+
+/// Renders the DataFrame as described by `spec` onto any plotters backend
+pub fn plot_on_backend<B>(
+    spec: &PlotSpec,
+    df: &DataFrame,
+    backend: B,
+) -> Result<PlotReport, PlotError>
+where
+    B: DrawingBackend,
+{
+    let root = backend.into_drawing_area();
+    root.fill(&WHITE)
+        .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+    let root = match &spec.title
+    {
+        Some(title) => root
+            .titled(title, (spec.title_font.as_str(), spec.title_font_size))
+            .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?,
+        None => root,
+    };
+
+    let spec = &resolve_defaults(spec, df)?;
+    // colors come from all rows, so they agree between panels
+    let facet = spec
+        .color
+        .map(|color_facet_index| {
+            Facet::new(
+                column(df, color_facet_index, "Color")?,
+                &spec.palette,
+                spec.color_order,
+                &spec.color_map,
+            )
+        })
+        .transpose()?;
+    // the color facet takes precedence over the gradient, histograms color by facet only
+    let gradient = match (spec.gradient, &facet, &spec.hist)
+    {
+        (Some(color_gradient_index), None, None) =>
+        {
+            let gradient_series = column(df, color_gradient_index, "Gradient")?;
+            let scale = GradientScale::new(
+                gradient_series,
+                &spec.gradient_scheme,
+                (spec.gradient_min, spec.gradient_max),
+                spec.gradient_log,
+            )?;
+            Some((gradient_series, scale))
+        }
+        _ => None,
+    };
+    let panels = facet_panels(spec, df)?;
+    // a shared categorical X axis has the slots of all rows in every panel
+    let shared_x = panels.is_some() && spec.facet_scales.shared_x() && spec.hist.is_none();
+    let shared_x_categories = match spec.y.first()
+    {
+        Some(&y) if shared_x =>
+        {
+            let x = x_column(df, spec)?;
+            x_order(spec, &x)
+                .map(|order| category::slots(&x, column(df, y, "Y")?, order))
+                .transpose()?
+                .map(|(_, names)| names)
+        }
+        _ => None,
+    };
+    let legend_categories = facet
+        .as_ref()
+        .map(|facet| facet.categories.clone())
+        .unwrap_or_default();
+
+    // the series of a panel borrow its rows rather than copying them
+    let mut panel_rows: Vec<PanelRows> = Vec::new();
+    match &panels
+    {
+        Some(panels) =>
+        {
+            for mask in &panels.masks
+            {
+                panel_rows.push(PanelRows::new(
+                    df.filter(mask)?,
+                    spec,
+                    facet.as_ref().map(|facet| facet.filter(mask)),
+                    gradient
+                        .as_ref()
+                        .map(|(series, _)| series.filter(mask))
+                        .transpose()?,
+                    shared_x_categories.as_deref(),
+                )?);
+            }
+        }
+        None => panel_rows.push(PanelRows::new(
+            df.clone(),
+            spec,
+            facet,
+            gradient.as_ref().map(|(series, _)| (*series).clone()),
+            None,
+        )?),
+    }
+    let scale = gradient.as_ref().map(|(_, scale)| scale);
+    let mut contents: Vec<PanelContent> = Vec::with_capacity(panel_rows.len());
+    for rows in &panel_rows
+    {
+        contents.push(panel_content(rows, spec, scale)?);
+    }
+
+    let mut report = PlotReport::default();
+    for (i, content) in contents.iter().enumerate()
+    {
+        for (label, error) in content.series.iter().flat_map(|s| &s.skipped_fits)
+        {
+            let label = match &panels
+            {
+                Some(panels) => format!("{} {}", panels.names[i], label),
+                None => label.clone(),
+            };
+            report
+                .warnings
+                .push(format!("no fit for {}: {}", label, error));
+        }
+    }
+
+    if spec.fit_print
+    {
+        for (i, content) in contents.iter().enumerate()
+        {
+            for curve in content.series.iter().flat_map(|s| &s.fits)
+            {
+                match &panels
+                {
+                    Some(panels) =>
+                    {
+                        println!("{} {}: {}", panels.names[i], curve.label, curve.fitted.describe())
+                    }
+                    None => println!("{}: {}", curve.label, curve.fitted.describe()),
+                }
+            }
+        }
+    }
+
+    let no_data = |axis, log| {
+        PlotError::InvalidData(
+            if log
+            {
+                format!("No positive data in {} column for a logarithmic axis", axis)
+            }
+            else
+            {
+                format!("No data in {} column", axis)
+            },
+        )
+    };
+    let widen = |range: Option<(f64, f64)>, (min, max): (f64, f64)| {
+        Some(range.map_or((min, max), |(a, b)| (a.min(min), b.max(max))))
+    };
+    let x_range = contents
+        .iter()
+        .filter_map(|content| content.x_range)
+        .fold(None, widen)
+        .ok_or_else(|| no_data("X", spec.logx))?;
+    let y_range = contents
+        .iter()
+        .filter_map(|content| content.y_range)
+        .fold(None, widen)
+        .ok_or_else(|| no_data("Y", spec.logy))?;
+
+    // density cells are colored by count, on one scale for all panels
+    let density = if spec.shape.is_density()
+    {
+        let counts: Series = contents
+            .iter()
+            .flat_map(|content| &content.series)
+            .flat_map(|s| &s.cells)
+            .map(|(cell, _)| cell.count)
+            .collect();
+        let scale = GradientScale::new(
+            &counts,
+            &spec.gradient_scheme,
+            (spec.gradient_min, spec.gradient_max),
+            spec.gradient_log,
+        )?;
+        for s in contents.iter_mut().flat_map(|content| &mut content.series)
+        {
+            for (cell, style) in s.cells.iter_mut()
+            {
+                *style = scale.color_at(cell.count, 1.0).filled();
+            }
+        }
+        Some(scale)
+    }
+    else
+    {
+        None
+    };
+
+    // several series and the color facet categories each get a legend entry
+    let mut legend: Vec<LegendEntry> = Vec::new();
+    if contents[0].series.len() > 1
+    {
+        legend.extend(
+            contents[0]
+                .series
+                .iter()
+                .map(|s| (s.label.clone(), s.legend_style)),
+        );
+    }
+    // histogram groups are series of their own
+    if spec.hist.is_none()
+    {
+        legend.extend(
+            legend_categories
+                .into_iter()
+                .map(|(name, color)| (name, color.filled())),
+        );
+    }
+    let legend_inside =
+        spec.legend && !legend.is_empty() && spec.legend_position != LegendPosition::Outside;
+    let legend_outside =
+        spec.legend && !legend.is_empty() && spec.legend_position == LegendPosition::Outside;
+
+    // legend outside and color bar are placed right of the panels
+    let (width, _) = root.dim_in_pixel();
+    let legend_width = if legend_outside
+    {
+        outside_legend_width(spec, &legend).min(width / 3)
+    }
+    else
+    {
+        0
+    };
+    let colorbar = gradient
+        .as_ref()
+        .map(|(series, scale)| (series.name().as_str(), scale))
+        .or(density.as_ref().map(|scale| ("Count", scale)));
+    let colorbar_width = if spec.legend && colorbar.is_some()
+    {
+        colorbar_width(spec).min(width / 3)
+    }
+    else
+    {
+        0
+    };
+    let (grid_area, side_area) = root.split_horizontally(width - legend_width - colorbar_width);
+    let (legend_area, colorbar_area) = side_area.split_horizontally(legend_width);
+
+    let (rows, columns) = panels
+        .as_ref()
+        .map_or((1, 1), |panels| (panels.rows, panels.columns));
+    let panel_areas = grid_area.split_evenly((rows, columns));
+    // rotated X labels may need a higher label area, the color bar ends level with the X axis
+    let mut x_label_area = spec.xdesc_area;
+    for (i, (content, panel_area)) in contents.iter().zip(&panel_areas).enumerate()
+    {
+        let mut chart = ChartBuilder::on(panel_area);
+        chart.y_label_area_size(spec.ydesc_area).margin(26u32);
+        if let Some(panels) = &panels
+        {
+            chart.caption(&panels.names[i], (spec.title_font.as_str(), spec.title_font_size));
+        }
+        // a free axis falls back to the shared range in a panel without data
+        let panel_x_range = match content.x_range
+        {
+            Some(range) if !spec.facet_scales.shared_x() => range,
+            _ => x_range,
+        };
+        let panel_y_range = match content.y_range
+        {
+            Some(range) if !spec.facet_scales.shared_y() => range,
+            _ => y_range,
+        };
+        let panel_x_label_area = plot_shapes(
+            &mut chart,
+            &content.series,
+            legend_inside.then_some(&legend[..]).filter(|_| i == 0),
+            spec,
+            panel_x_range,
+            content.x_categories,
+            panel_y_range,
+        )?;
+        x_label_area = x_label_area.max(panel_x_label_area);
+    }
+
+    if legend_outside
+    {
+        // an empty chart next to the plot, so the legend is not clipped to the plotting area
+        let mut legend_chart = ChartBuilder::on(&legend_area)
+            .margin_top(26u32)
+            .margin_right(26u32)
+            .build_cartesian_2d(0f64..1f64, 0f64..1f64)
+            .map_err(|e| PlotError::InvalidData(format!("Grid creation error: {}", e)))?;
+        draw_legend(&mut legend_chart, spec, &legend, SeriesLabelPosition::UpperLeft)?;
+    }
+
+    if let (Some((name, scale)), true) = (colorbar, colorbar_width > 0)
+    {
+        draw_colorbar(&colorbar_area, spec, name, scale, x_label_area)?;
+    }
+
+    root.present()
+        .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+    Ok(report)
+}
+
+/// Rows of one panel with their color facet categories, gradient values and X values
+struct PanelRows
+{
+    df: DataFrame,
+    facet: Option<Facet>,
+    gradient: Option<Float64Chunked>,
+    /// X as numbers, times or category slots with the category names, None for histograms
+    x: Option<(Series, Option<Vec<String>>)>,
+}
+
+impl PanelRows
+{
+    /// `x_categories` fixes the slots of a categorical X axis, else the rows in `df` decide
+    fn new(
+        df: DataFrame,
+        spec: &PlotSpec,
+        facet: Option<Facet>,
+        gradient: Option<Series>,
+        x_categories: Option<&[String]>,
+    ) -> Result<PanelRows, PlotError>
+    {
+        // looked up per row while drawing
+        let gradient = gradient
+            .map(|series| {
+                let values = series.cast(&DataType::Float64)?;
+                let values = values.f64().map_err(|_| {
+                    PlotError::InvalidData("Gradient column is not numeric".to_string())
+                })?;
+                Ok::<_, PlotError>(values.rechunk().into_owned())
+            })
+            .transpose()?;
+        let x = match spec.hist
+        {
+            Some(_) => None,
+            None => Some(xy_x(&df, spec, x_categories)?),
+        };
+        Ok(PanelRows {
+            df,
+            facet,
+            gradient,
+            x,
+        })
+    }
+}
+
+/// Series of one panel and the range of their data
+struct PanelContent<'a>
+{
+    series: Vec<PlotSeries<'a>>,
+    x_categories: Option<&'a [String]>,
+    x_range: Option<(f64, f64)>,
+    y_range: Option<(f64, f64)>,
+}
+
+/// Panels of the facet column, None without one
+fn facet_panels(spec: &PlotSpec, df: &DataFrame) -> Result<Option<Panels>, PlotError>
+{
+    spec.facet
+        .map(|facet_index| {
+            panel::panels(
+                column(df, facet_index, "Facet")?,
+                spec.color_order,
+                spec.facet_rows,
+                spec.facet_columns,
+            )
+        })
+        .transpose()
+}
+
+/// Series of the rows of a panel
+fn panel_content<'a>(
+    rows: &'a PanelRows,
+    spec: &PlotSpec,
+    scale: Option<&'a GradientScale>,
+) -> Result<PanelContent<'a>, PlotError>
+{
+    let (series, x_categories) = match (&spec.hist, &rows.x)
+    {
+        (Some(_), _) if spec.fit.is_some() =>
+        {
+            return Err(PlotError::InvalidData(
+                "A fit cannot be combined with a histogram".to_string(),
+            ))
+        }
+        (Some(hist), _) => (hist_series(&rows.df, spec, hist, rows.facet.as_ref())?, None),
+        (None, Some((x, x_categories))) =>
+        {
+            (xy_series(rows, x, spec, scale, x_categories.is_some())?, x_categories.as_deref())
+        }
+        (None, None) => unreachable!("only histograms leave out the X values"),
+    };
+    let widen = |range: Option<(f64, f64)>, (min, max): (f64, f64)| {
+        Some(range.map_or((min, max), |(a, b)| (a.min(min), b.max(max))))
+    };
+    let mut x_range: Option<(f64, f64)> = None;
+    let mut y_range: Option<(f64, f64)> = None;
+    for s in &series
+    {
+        // columns with explicit extents reach beyond their center
+        let x_extent = match &s.columns
+        {
+            Some(columns) =>
+            {
+                let edges: Series = columns.iter().flat_map(|c| [c.0, c.1]).collect();
+                extent(&edges, None, spec.logx)?
+            }
+            // centered columns are as wide as the slots between X values
+            None if spec.shape == Shape::Column && !spec.logx =>
+            {
+                let half_width = column_half_width(&s.x)?;
+                extent(&s.x, s.x_bounds.as_ref(), false)?
+                    .map(|(min, max)| (min - half_width, max + half_width))
+            }
+            None => extent(&s.x, s.x_bounds.as_ref(), spec.logx)?,
+        };
+        if let Some(x_extent) = x_extent
+        {
+            x_range = widen(x_range, x_extent);
+        }
+        if let Some(y_extent) = extent(&s.y, s.y_bounds.as_ref(), spec.logy)?
+        {
+            y_range = widen(y_range, y_extent);
+        }
+    }
+    Ok(PanelContent {
+        series,
+        x_categories,
+        x_range,
+        y_range,
+    })
+}
+
+/// Legend label with the style of its symbol
+type LegendEntry = (String, ShapeStyle);
+
+/// X/Y pair, either may be missing
+type Point = (Option<f64>, Option<f64>);
+
+/// Row, point and style of a point on a line
+type LinePoint = (usize, Point, ShapeStyle);
+
+/// Connected points of a line with their style
+type Polyline = (Vec<(f64, f64)>, ShapeStyle);
+
+/// Style of each row, looked up while drawing instead of stored per row
+enum RowStyles<'a>
+{
+    /// the same style in every row
+    Plain(ShapeStyle),
+    /// style of the color facet category of each row
+    Facet
+    {
+        categories: Vec<ShapeStyle>,
+        indices: Cow<'a, [usize]>,
+    },
+    /// gradient color of the value in each row
+    Gradient
+    {
+        values: &'a Float64Chunked,
+        scale: &'a GradientScale,
+        alpha: f64,
+    },
+}
+
+impl RowStyles<'_>
+{
+    fn style(&self, row: usize) -> ShapeStyle
+    {
+        match self
+        {
+            RowStyles::Plain(style) => *style,
+            RowStyles::Facet {
+                categories,
+                indices,
+            } => categories[indices[row]],
+            RowStyles::Gradient {
+                values,
+                scale,
+                alpha,
+            } => scale
+                .color_at(values.get(row).unwrap_or(0.0), *alpha)
+                .filled(),
+        }
+    }
+}
+
+/// Label of a curve that could not be fitted, with the reason
+type SkippedFit = (String, PlotError);
+
+/// One Y column to be drawn against X
+struct PlotSeries<'a>
+{
+    label: String,
+    x: Cow<'a, Series>,
+    y: Series,
+    styles: RowStyles<'a>,
+    /// color facet group per row, lines connect only points of the same group
+    groups: Option<Cow<'a, [usize]>>,
+    legend_style: ShapeStyle,
+    /// explicit left, right and base of each column, e.g. histogram bins
+    columns: Option<Vec<(f64, f64, f64)>>,
+    /// lower and upper end of an error bar per point
+    x_bounds: Option<(Series, Series)>,
+    y_bounds: Option<(Series, Series)>,
+    /// fitted curves, one per color facet category present
+    fits: Vec<FitCurve>,
+    /// curves that could not be fitted
+    skipped_fits: Vec<SkippedFit>,
+    /// occupied density cells, styled once the count scale of all panels is known
+    cells: Vec<(Cell, ShapeStyle)>,
+}
+
+/// Fitted curve of a series or of one of its color facet categories
+struct FitCurve
+{
+    label: String,
+    fitted: Fitted,
+    style: ShapeStyle,
+}
+
+impl PlotSeries<'_>
+{
+    /// Returns an iterator over x/y points and their style
+    fn xyc(&self) -> Result<impl Iterator<Item = (Point, ShapeStyle)> + '_, PlotError>
+    {
+        let xy = self
+            .x
+            .f64()
+            .map_err(|_| PlotError::InvalidData("X column is not numeric".to_string()))?
+            .into_iter()
+            .zip(
+                self.y
+                    .f64()
+                    .map_err(|_| PlotError::InvalidData("Y column is not numeric".to_string()))?,
+            );
+        Ok(xy
+            .enumerate()
+            .map(|(row, point)| (point, self.styles.style(row))))
+    }
+
+    /// Points in line order, one list per facet group
+    fn line_groups(&self, spec: &PlotSpec) -> Result<Vec<Vec<LinePoint>>, PlotError>
+    {
+        let mut groups: BTreeMap<usize, Vec<LinePoint>> = BTreeMap::new();
+        for (row, (point, style)) in self.xyc()?.enumerate()
+        {
+            let group = self.groups.as_ref().map_or(0, |groups| groups[row]);
+            groups.entry(group).or_default().push((row, point, style));
+        }
+        let mut groups: Vec<_> = groups.into_values().collect();
+        if spec.sort_x
+        {
+            for group in groups.iter_mut()
+            {
+                group.retain(|(_, (x, _), _)| x.is_some());
+                group.sort_by(|(_, (a, _), _), (_, (b, _), _)| {
+                    a.unwrap_or(0.0).total_cmp(&b.unwrap_or(0.0))
+                });
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Connected runs of points with their style, one or more per facet group.
+    ///
+    /// Runs break at missing values. With a gradient every segment gets the color of its start.
+    fn polylines(&self, spec: &PlotSpec) -> Result<Vec<Polyline>, PlotError>
+    {
+        let segmented = spec.color.is_none() && spec.gradient.is_some();
+        let mut polylines = Vec::new();
+        for group in self.line_groups(spec)?
+        {
+            let mut run: Vec<(f64, f64)> = Vec::new();
+            let mut run_style = None;
+            for (_, (x, y), style) in group
+            {
+                match (x, y, run_style)
+                {
+                    (Some(x), Some(y), Some(start_style)) if segmented =>
+                    {
+                        polylines.push((vec![run[run.len() - 1], (x, y)], start_style));
+                        run = vec![(x, y)];
+                        run_style = Some(style);
+                    }
+                    (Some(x), Some(y), _) =>
+                    {
+                        run.push((x, y));
+                        run_style.get_or_insert(style);
+                    }
+                    _ =>
+                    {
+                        if let (true, Some(style)) = (run.len() > 1 && !segmented, run_style)
+                        {
+                            polylines.push((std::mem::take(&mut run), style));
+                        }
+                        run.clear();
+                        run_style = None;
+                    }
+                }
+            }
+            if let (true, Some(style)) = (run.len() > 1 && !segmented, run_style)
+            {
+                polylines.push((run, style));
+            }
+        }
+
+        let line_width = spec.line_width;
+        Ok(polylines
+            .into_iter()
+            .map(|(points, style)| (points, style.color.stroke_width(line_width)))
+            .collect())
+    }
+
+    /// Outlines of the shaded area between the lower and upper Y bounds along the lines.
+    ///
+    /// Like the lines, bands break at missing values and take the color of their first point.
+    fn bands(&self, spec: &PlotSpec) -> Result<Vec<Polyline>, PlotError>
+    {
+        let Some(bounds) = &self.y_bounds
+        else
+        {
+            return Ok(Vec::new());
+        };
+        let bounds: Vec<_> = bound_values(bounds)?.collect();
+        let mut bands = Vec::new();
+        for group in self.line_groups(spec)?
+        {
+            let mut run: Vec<(f64, f64, f64)> = Vec::new();
+            let mut run_style = None;
+            for (row, (x, _), style) in group
+            {
+                match (x, bounds[row])
+                {
+                    (Some(x), (Some(low), Some(high))) =>
+                    {
+                        run.push((x, low, high));
+                        run_style.get_or_insert(style);
+                    }
+                    _ =>
+                    {
+                        if let (true, Some(style)) = (run.len() > 1, run_style.take())
+                        {
+                            bands.push((band_outline(&run), style));
+                        }
+                        run.clear();
+                    }
+                }
+            }
+            if let (true, Some(style)) = (run.len() > 1, run_style)
+            {
+                bands.push((band_outline(&run), style));
+            }
+        }
+        Ok(bands
+            .into_iter()
+            .map(|(outline, style)| {
+                (outline, RGBAColor(style.color.0, style.color.1, style.color.2, 0.2).filled())
+            })
+            .collect())
+    }
+}
+
+/// Along the upper ends and back along the lower ends
+fn band_outline(run: &[(f64, f64, f64)]) -> Vec<(f64, f64)>
+{
+    run.iter()
+        .map(|&(x, _, high)| (x, high))
+        .chain(run.iter().rev().map(|&(x, low, _)| (x, low)))
+        .collect()
+}
+
+/// Lower and upper end of each point
+fn bound_values(
+    (low, high): &(Series, Series),
+) -> Result<impl Iterator<Item = (Option<f64>, Option<f64>)> + '_, PlotError>
+{
+    let not_numeric = |_| PlotError::InvalidData("Error bounds are not numeric".to_string());
+    Ok(low
+        .f64()
+        .map_err(not_numeric)?
+        .into_iter()
+        .zip(high.f64().map_err(not_numeric)?))
+}
+
+/// Smallest and largest of `values` and the ends of their error bounds
+///
+/// A logarithmic axis only shows positive values, infinite values show nowhere.
+fn extent(
+    values: &Series,
+    bounds: Option<&(Series, Series)>,
+    log: bool,
+) -> Result<Option<(f64, f64)>, PlotError>
+{
+    let mut parts = vec![values];
+    if let Some((low, high)) = bounds
+    {
+        parts.extend([low, high]);
+    }
+    let mut range: Option<(f64, f64)> = None;
+    for part in parts
+    {
+        let part = part
+            .f64()
+            .map_err(|_| PlotError::InvalidData("Plotted column is not numeric".to_string()))?;
+        range = part
+            .into_iter()
+            .flatten()
+            .filter(|v| v.is_finite() && (!log || *v > 0.0))
+            .fold(range, |range, v| Some(range.map_or((v, v), |(a, b)| (a.min(v), b.max(v)))));
+    }
+    Ok(range)
+}
+
+/// Copy of `spec` with the axis descriptions defaulting to the header names
+/// and the shape and X scale a histogram implies
+fn resolve_defaults(spec: &PlotSpec, df: &DataFrame) -> Result<PlotSpec, PlotError>
+{
+    let xdesc = match &spec.xdesc
+    {
+        Some(xdesc) => xdesc.clone(),
+        None if spec.x == 0 => "Row".to_string(),
+        None => column(df, spec.x, "X")?.name().to_string(),
+    };
+    let ydesc = match (&spec.ydesc, &spec.hist)
+    {
+        (Some(ydesc), _) => ydesc.clone(),
+        (None, Some(hist)) if hist.density => "Density".to_string(),
+        (None, Some(_)) => "Count".to_string(),
+        (None, None) =>
+        {
+            let names = spec
+                .y
+                .iter()
+                .map(|&y| Ok(column(df, y, "Y")?.name().to_string()))
+                .collect::<Result<Vec<_>, PlotError>>()?
+                .join(", ");
+            match spec.aggregate
+            {
+                Some(aggregate) => format!("{} of {}", aggregate.name(), names),
+                None => names,
+            }
+        }
+    };
+    let (shape, logx) = match &spec.hist
+    {
+        // lines give a frequency polygon, everything else bars
+        Some(hist) if spec.shape.has_line() => (spec.shape, spec.logx || hist.log),
+        Some(hist) => (Shape::Column, spec.logx || hist.log),
+        None => (spec.shape, spec.logx),
+    };
+    Ok(PlotSpec {
+        xdesc: Some(xdesc),
+        ydesc: Some(ydesc),
+        shape,
+        logx,
+        ..spec.clone()
+    })
+}
+
+/// The X column, or the row numbers for X 0
+fn x_column(df: &DataFrame, spec: &PlotSpec) -> Result<Series, PlotError>
+{
+    if spec.x == 0
+    {
+        Ok((0..df.height() as i64).collect())
+    }
+    else
+    {
+        Ok(column(df, spec.x, "X")?.clone())
+    }
+}
+
+/// Order of the slots if X is categorical, string X columns are unless parsed as time
+fn x_order(spec: &PlotSpec, x: &Series) -> Option<XOrder>
+{
+    match spec.x_categories
+    {
+        _ if spec.x_time.is_some() => None,
+        None if x.dtype() == &DataType::String => Some(XOrder::Input),
+        order => order,
+    }
+}
+
+/// X values of the rows in `df`, the X column may be parsed as time or categories.
+///
+/// Categorical X gets the slots of `x_categories` if given, else those of the rows in `df`,
+/// and returns the category names with the slots.
+fn xy_x(
+    df: &DataFrame,
+    spec: &PlotSpec,
+    x_categories: Option<&[String]>,
+) -> Result<(Series, Option<Vec<String>>), PlotError>
+{
+    let x = &x_column(df, spec)?;
+    if spec.y.is_empty()
+    {
+        return Err(PlotError::InvalidColumn("No Y column given".to_string()));
+    }
+    Ok(match (&spec.x_time, x_order(spec, x), x_categories)
+    {
+        (Some(parse), _, _) => (time::epoch_seconds(x, parse)?, None),
+        (None, Some(_), Some(names)) => (category::slots_in(x, names)?, Some(names.to_vec())),
+        (None, Some(order), None) =>
+        {
+            let (slots, names) = category::slots(x, column(df, spec.y[0], "Y")?, order)?;
+            (slots, Some(names))
+        }
+        (None, None, _) => (x.cast(&DataType::Float64)?, None),
+    })
+}
+
+/// One series per Y column against the X values `xf64` of the panel
+fn xy_series<'a>(
+    rows: &'a PanelRows,
+    xf64: &'a Series,
+    spec: &PlotSpec,
+    scale: Option<&'a GradientScale>,
+    categorical: bool,
+) -> Result<Vec<PlotSeries<'a>>, PlotError>
+{
+    let df = &rows.df;
+    let facet = rows.facet.as_ref();
+    let gradient = rows.gradient.as_ref().zip(scale);
+    if spec.aggregate.is_some() && gradient.is_some()
+    {
+        return Err(PlotError::InvalidData(
+            "A gradient cannot be combined with aggregation".to_string(),
+        ));
+    }
+    if spec.aggregate.is_some() && (spec.x_error.is_some() || spec.y_error.is_some())
+    {
+        return Err(PlotError::InvalidData(
+            "Error columns cannot be combined with aggregation, use its error bars".to_string(),
+        ));
+    }
+    if spec.shape.is_density()
+    {
+        if facet.is_some() || gradient.is_some()
+        {
+            return Err(PlotError::InvalidData(
+                "Density shapes color cells by count, they cannot have a color facet or gradient"
+                    .to_string(),
+            ));
+        }
+        if spec.aggregate.is_some() || spec.y.len() > 1
+        {
+            return Err(PlotError::InvalidData(
+                "Density shapes need a single Y column without aggregation".to_string(),
+            ));
+        }
+    }
+    if spec.y_error.is_some() && spec.y.len() > 1
+    {
+        return Err(PlotError::InvalidData("Y error columns need a single Y column".to_string()));
+    }
+    let x_bounds = match spec.x_error
+    {
+        Some(_) if categorical =>
+        {
+            return Err(PlotError::InvalidData(
+                "A categorical X axis cannot have error bars".to_string(),
+            ))
+        }
+        Some(error) => Some(error_bounds(df, xf64, error, spec.x_time.as_ref(), "X")?),
+        None => None,
+    };
+
+    let mut series = Vec::with_capacity(spec.y.len());
+    for (i, &y_index) in spec.y.iter().enumerate()
+    {
+        let y = column(df, y_index, "Y")?;
+        // a single series keeps the plot color, several get one palette color each
+        let series_color = if spec.y.len() == 1
+        {
+            spec.plot_color
+        }
+        else
+        {
+            spec.palette.pick(i)
+        };
+        if let Some(aggregate) = spec.aggregate
+        {
+            let aggregated = aggregate::aggregate(
+                xf64,
+                y,
+                facet.map(|facet| &facet.indices[..]),
+                aggregate,
+                spec.error_bars,
+            )?;
+            let fit_groups = aggregated.groups.clone();
+            let styles = match (&aggregated.groups, facet)
+            {
+                (Some(groups), Some(facet)) => RowStyles::Facet {
+                    categories: facet_styles(facet),
+                    indices: Cow::Owned(groups.clone()),
+                },
+                _ => RowStyles::Plain(series_color.mix(spec.alpha).filled()),
+            };
+            let mut s = PlotSeries {
+                label: y.name().to_string(),
+                x: Cow::Owned(aggregated.x),
+                y: aggregated.y,
+                styles,
+                groups: aggregated
+                    .groups
+                    .filter(|_| spec.shape.has_line())
+                    .map(Cow::Owned),
+                legend_style: series_color.filled(),
+                columns: None,
+                x_bounds: None,
+                y_bounds: aggregated.bounds,
+                fits: Vec::new(),
+                skipped_fits: Vec::new(),
+                cells: Vec::new(),
+            };
+            (s.fits, s.skipped_fits) =
+                fit_curves(spec, &s, fit_groups.as_deref(), facet, series_color)?;
+            series.push(s);
+            continue;
+        }
+        let y = y.cast(&DataType::Float64)?;
+        let y_bounds = spec
+            .y_error
+            .map(|error| error_bounds(df, &y, error, None, "Y"))
+            .transpose()?;
+        let mut s = PlotSeries {
+            label: y.name().to_string(),
+            x: Cow::Borrowed(xf64),
+            y,
+            styles: row_styles(spec, facet, gradient, series_color),
+            groups: facet
+                .filter(|_| spec.shape.has_line())
+                .map(|facet| Cow::Borrowed(&facet.indices[..])),
+            legend_style: series_color.filled(),
+            columns: None,
+            x_bounds: x_bounds.clone(),
+            y_bounds,
+            fits: Vec::new(),
+            skipped_fits: Vec::new(),
+            cells: Vec::new(),
+        };
+        let fit_groups = facet.map(|facet| &facet.indices[..]);
+        (s.fits, s.skipped_fits) = fit_curves(spec, &s, fit_groups, facet, series_color)?;
+        if spec.shape.is_density()
+        {
+            let points: Vec<(f64, f64)> =
+                s.xyc()?.filter_map(|((x, y), _)| Some((x?, y?))).collect();
+            let size = CellSize {
+                count: spec.cells,
+                width: spec.cell_width,
+                height: spec.cell_height,
+                aspect: spec.height as f64 / spec.width as f64,
+            };
+            s.cells =
+                density::cells(&points, size, spec.shape == Shape::Hexbin, (spec.logx, spec.logy))
+                    .into_iter()
+                    .map(|cell| (cell, spec.plot_color.filled()))
+                    .collect();
+        }
+        series.push(s);
+    }
+    Ok(series)
+}
+
+/// Fits the points of the series, separately for each color facet category in `groups`.
+///
+/// Curves take the category color, several series prefix their label to the category name.
+/// Unstable fits are returned as their label with the error.
+fn fit_curves(
+    spec: &PlotSpec,
+    s: &PlotSeries,
+    groups: Option<&[usize]>,
+    facet: Option<&Facet>,
+    series_color: RGBColor,
+) -> Result<(Vec<FitCurve>, Vec<SkippedFit>), PlotError>
+{
+    let Some(kind) = spec.fit
+    else
+    {
+        return Ok((Vec::new(), Vec::new()));
+    };
+    let mut points: BTreeMap<usize, Vec<(f64, f64)>> = BTreeMap::new();
+    for (row, ((x, y), _)) in s.xyc()?.enumerate()
+    {
+        if let (Some(x), Some(y)) = (x, y)
+        {
+            let group = groups.map_or(0, |groups| groups[row]);
+            points.entry(group).or_default().push((x, y));
+        }
+    }
+    let mut curves = Vec::new();
+    let mut skipped = Vec::new();
+    for (group, points) in points
+    {
+        let (label, color) = match facet.filter(|_| groups.is_some())
+        {
+            Some(facet) =>
+            {
+                let (name, color) = &facet.categories[group];
+                let label = if spec.y.len() > 1
+                {
+                    format!("{} {}", s.label, name)
+                }
+                else
+                {
+                    name.clone()
+                };
+                (label, *color)
+            }
+            None => (s.label.clone(), series_color),
+        };
+        // an unstable fit of one category does not spoil the plot
+        let fitted = match fit::fit(&points, kind, spec.logx)
+        {
+            Ok(Some(fitted)) => fitted,
+            Ok(None) => continue,
+            Err(error) =>
+            {
+                skipped.push((label, error));
+                continue;
+            }
+        };
+        curves.push(FitCurve {
+            label,
+            fitted,
+            style: color.stroke_width(spec.line_width + 1),
+        });
+    }
+    Ok((curves, skipped))
+}
+
+/// Lower and upper end for each of `values` from the error columns of one axis.
+///
+/// Absolute ends are parsed like the X column on a time axis, distances are in seconds there.
+fn error_bounds(
+    df: &DataFrame,
+    values: &Series,
+    error: ErrorColumns,
+    time: Option<&TimeParse>,
+    axis: &str,
+) -> Result<(Series, Series), PlotError>
+{
+    let not_numeric =
+        |role: &str| PlotError::InvalidData(format!("{} column is not numeric", role));
+    let values = values.f64().map_err(|_| not_numeric(axis))?;
+    let end = |index: usize, role: String| -> Result<Float64Chunked, PlotError> {
+        let series = column(df, index, &role)?;
+        let series = match time
+        {
+            Some(parse) => time::epoch_seconds(series, parse)?,
+            None => series.cast(&DataType::Float64)?,
+        };
+        Ok(series.f64().map_err(|_| not_numeric(&role))?.clone())
+    };
+    let (low, high) = match error
+    {
+        ErrorColumns::Symmetric(index) =>
+        {
+            let role = format!("{} error", axis);
+            let distance = column(df, index, &role)?.cast(&DataType::Float64)?;
+            let distance = distance
+                .f64()
+                .map_err(|_| not_numeric(&role))?
+                .apply_values(f64::abs);
+            (values - &distance, values + &distance)
+        }
+        ErrorColumns::Range { low, high } => (
+            low.map_or_else(|| Ok(values.clone()), |low| end(low, format!("{} low", axis)))?,
+            high.map_or_else(|| Ok(values.clone()), |high| end(high, format!("{} high", axis)))?,
+        ),
+    };
+    Ok((low.with_name("low".into()).into_series(), high.with_name("high".into()).into_series()))
+}
+
+/// One series of bins per color facet category, or a single one in the plot color
+fn hist_series(
+    df: &DataFrame,
+    spec: &PlotSpec,
+    hist: &Histogram,
+    facet: Option<&Facet>,
+) -> Result<Vec<PlotSeries<'static>>, PlotError>
+{
+    let values = column(df, spec.x, "Histogram")?;
+    let binned = hist::bin(
+        values,
+        facet.map(|facet| &facet.indices[..]),
+        facet.map_or(1, |facet| facet.categories.len()),
+        hist,
+    )?;
+    let groups: Vec<(String, RGBColor)> = match facet
+    {
+        Some(facet) => facet.categories.clone(),
+        None => vec![(values.name().to_string(), spec.plot_color)],
+    };
+    let edges = &binned.edges;
+    let centers: Vec<f64> = edges
+        .windows(2)
+        .map(|edge| {
+            if hist.log
+            {
+                (edge[0] * edge[1]).sqrt()
+            }
+            else
+            {
+                (edge[0] + edge[1]) / 2.0
+            }
+        })
+        .collect();
+
+    let mut base = vec![0.0; centers.len()];
+    let mut series = Vec::with_capacity(groups.len());
+    for ((label, color), values) in groups.into_iter().zip(binned.values)
+    {
+        let top: Vec<f64> = if hist.stack
+        {
+            base.iter().zip(&values).map(|(b, v)| b + v).collect()
+        }
+        else
+        {
+            values
+        };
+        let columns = (0..centers.len())
+            .map(|i| (edges[i], edges[i + 1], if hist.stack { base[i] } else { 0.0 }))
+            .collect();
+        series.push(PlotSeries {
+            label,
+            x: Cow::Owned(Series::new("x".into(), &centers)),
+            y: Series::new("y".into(), &top),
+            styles: RowStyles::Plain(color.mix(spec.alpha).filled()),
+            groups: None,
+            legend_style: color.filled(),
+            columns: Some(columns),
+            x_bounds: None,
+            y_bounds: None,
+            fits: Vec::new(),
+            skipped_fits: Vec::new(),
+            cells: Vec::new(),
+        });
+        if hist.stack
+        {
+            base = top;
+        }
+    }
+    Ok(series)
+}
+
+/// Style of the rows by color facet, gradient or the series color
+fn row_styles<'a>(
+    spec: &PlotSpec,
+    facet: Option<&'a Facet>,
+    gradient: Option<(&'a Float64Chunked, &'a GradientScale)>,
+    series_color: RGBColor,
+) -> RowStyles<'a>
+{
+    if let Some(facet) = facet
+    {
+        RowStyles::Facet {
+            categories: facet_styles(facet),
+            indices: Cow::Borrowed(&facet.indices),
+        }
+    }
+    else if let Some((values, scale)) = gradient
+    {
+        RowStyles::Gradient {
+            values,
+            scale,
+            alpha: spec.alpha,
+        }
+    }
+    else
+    {
+        RowStyles::Plain(series_color.mix(spec.alpha).filled())
+    }
+}
+
+/// Style of each color facet category
+fn facet_styles(facet: &Facet) -> Vec<ShapeStyle>
+{
+    facet
+        .categories
+        .iter()
+        .map(|(_, color)| color.filled())
+        .collect()
+}
+
+fn plot_shapes<DB>(
+    chart: &mut ChartBuilder<'_, '_, DB>,
+    series: &[PlotSeries],
+    legend: Option<&[LegendEntry]>,
+    spec: &PlotSpec,
+    (x_min, x_max): (f64, f64),
+    x_categories: Option<&[String]>,
+    (y_min, y_max): (f64, f64),
+) -> Result<u32, PlotError>
+where
+    DB: DrawingBackend,
+{
+    let x_auto = axis::auto_range(x_min, x_max, spec.x_range, spec.logx, false);
+    // columns grow from zero
+    let y_auto =
+        axis::auto_range(y_min, y_max, spec.y_range, spec.logy, spec.shape == Shape::Column);
+    let x_dim_min = spec.x_dim_min.unwrap_or(x_auto.start);
+    let x_dim_max = spec.x_dim_max.unwrap_or(x_auto.end);
+    let y_dim_min = spec.y_dim_min.unwrap_or(y_auto.start);
+    let y_dim_max = spec.y_dim_max.unwrap_or(y_auto.end);
+    for (axis, log, min, max) in [
+        ("X", spec.logx, x_dim_min, x_dim_max),
+        ("Y", spec.logy, y_dim_min, y_dim_max),
+    ]
+    {
+        if log && min <= 0.0
+        {
+            return Err(PlotError::InvalidData(format!(
+                "Minimum of the logarithmic {} axis must be positive, got {}",
+                axis, min
+            )));
+        }
+        if min >= max
+        {
+            return Err(PlotError::InvalidData(format!(
+                "Minimum {} of the {} axis is not below its maximum {}",
+                min, axis, max
+            )));
+        }
+    }
+    let y_range = y_dim_min..y_dim_max;
+
+    if let Some(names) = x_categories
+    {
+        if spec.logx
+        {
+            return Err(PlotError::InvalidData(
+                "A categorical X axis cannot be logarithmic".to_string(),
+            ));
+        }
+        plot_on_x_axis(chart, CategoryAxis::new(names), y_range, series, legend, spec)
+    }
+    else if spec.x_time.is_some()
+    {
+        if let Some(format) = &spec.time_format
+        {
+            time::check_format(format)?;
+        }
+        // a time axis spans the data, starting at the epoch would squash it
+        let padding = match spec.x_range
+        {
+            AxisRange::Data => 0.0,
+            AxisRange::Auto | AxisRange::Nice => ((x_max - x_min) * 0.02).max(0.5),
+        };
+        let x_axis = TimeAxis::new(
+            spec.x_dim_min.unwrap_or(x_min - padding)..spec.x_dim_max.unwrap_or(x_max + padding),
+            spec.time_format.as_deref(),
+        );
+        plot_on_x_axis(chart, x_axis, y_range, series, legend, spec)
+    }
+    else if spec.logx
+    {
+        plot_on_x_axis(chart, (x_dim_min..x_dim_max).log_scale(), y_range, series, legend, spec)
+    }
+    else
+    {
+        plot_on_x_axis(chart, x_dim_min..x_dim_max, y_range, series, legend, spec)
+    }
+}
+
+/// Builds the grid on the given X axis with a linear or logarithmic Y axis and draws on it,
+/// returns the height of the X label area
+fn plot_on_x_axis<DB, X>(
+    chart: &mut ChartBuilder<'_, '_, DB>,
+    x_axis: X,
+    y_range: std::ops::Range<f64>,
+    series: &[PlotSeries],
+    legend: Option<&[LegendEntry]>,
+    spec: &PlotSpec,
+) -> Result<u32, PlotError>
+where
+    DB: DrawingBackend,
+    X: AsRangedCoord<Value = f64>,
+    X::CoordDescType: ValueFormatter<f64>,
+{
+    let x_axis: X::CoordDescType = x_axis.into();
+    let x_label_area = if spec.rotate_x_labels
+    {
+        let labels: Vec<String> = x_axis
+            .key_points(BoldPoints(X_LABELS))
+            .iter()
+            .map(|x| x_label(spec, &x_axis, x))
+            .collect();
+        rotated_x_label_area(spec, &labels)
+    }
+    else
+    {
+        spec.xdesc_area
+    };
+    chart.x_label_area_size(x_label_area);
+    if spec.logy
+    {
+        let mut grid = chart
+            .build_cartesian_2d(x_axis, y_range.log_scale())
+            .map_err(|e| PlotError::InvalidData(format!("Grid creation error: {}", e)))?;
+        draw_on_grid(&mut grid, spec, series, legend)?;
+    }
+    else
+    {
+        let mut grid = chart
+            .build_cartesian_2d(x_axis, y_range)
+            .map_err(|e| PlotError::InvalidData(format!("Grid creation error: {}", e)))?;
+        draw_on_grid(&mut grid, spec, series, legend)?;
+    }
+    Ok(x_label_area)
+}
+
+/// Draws mesh and shapes, generic over linear and logarithmic axes
+fn draw_on_grid<'a, DB, X, Y>(
+    grid: &mut ChartContext<'a, DB, Cartesian2d<X, Y>>,
+    spec: &PlotSpec,
+    series: &[PlotSeries],
+    legend: Option<&[LegendEntry]>,
+) -> Result<(), PlotError>
+where
+    DB: DrawingBackend + 'a,
+    X: Ranged<ValueType = f64> + ValueFormatter<f64>,
+    Y: Ranged<ValueType = f64> + ValueFormatter<f64>,
+{
+    let si_formatter = |v: &f64| format_si_number(*v);
+    // rotated labels read bottom up and end at the axis
+    let rotated_label_style =
+        TextStyle::from((spec.label_font.as_str(), spec.label_font_size).into_font())
+            .transform(FontTransform::Rotate270)
+            .pos(Pos::new(HPos::Right, VPos::Center));
+    let mut mesh = grid.configure_mesh();
+    mesh.disable_x_mesh()
+        .bold_line_style(WHITE.mix(0.3))
+        .y_desc(spec.ydesc.as_deref().unwrap_or_default())
+        .x_desc(spec.xdesc.as_deref().unwrap_or_default())
+        .label_style((spec.label_font.as_str(), spec.label_font_size))
+        .axis_desc_style((spec.axis_desc_font.as_str(), spec.axis_desc_font_size));
+    if spec.si_format_x
+    {
+        mesh.x_label_formatter(&si_formatter);
+    }
+    // plotters centers rotated labels on the tick, they are drawn below instead
+    let no_label = |_: &f64| String::new();
+    if spec.rotate_x_labels
+    {
+        mesh.x_label_formatter(&no_label);
+    }
+    if spec.si_format_y
+    {
+        mesh.y_label_formatter(&si_formatter);
+    }
+    mesh.draw()
+        .map_err(|e| PlotError::InvalidData(format!("Draw error: {}", e)))?;
+    if spec.rotate_x_labels
+    {
+        draw_rotated_x_labels(grid, spec, rotated_label_style)?;
+    }
+
+    for s in series
+    {
+        // bands lie below the lines
+        if spec.shape.has_line()
+        {
+            for (outline, style) in s.bands(spec)?
+            {
+                grid.draw_series(std::iter::once(Polygon::new(outline, style)))
+                    .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+            }
+        }
+        match spec.shape
+        {
+            Shape::Column => draw_columns(grid, s)?,
+            Shape::Hexbin | Shape::Heat2d => draw_cells(grid, s)?,
+            Shape::Circle => draw_points(grid, spec, s)?,
+            Shape::Line => draw_lines(grid, spec, s)?,
+            Shape::LinePoints =>
+            {
+                draw_lines(grid, spec, s)?;
+                draw_points(grid, spec, s)?;
+            }
+        }
+        draw_error_bars(grid, spec, s)?;
+    }
+
+    // fitted curves lie above all points
+    for curve in series.iter().flat_map(|s| &s.fits)
+    {
+        let points = curve
+            .fitted
+            .curve
+            .iter()
+            .copied()
+            .filter(|(_, y)| !spec.logy || *y > 0.0);
+        grid.draw_series(LineSeries::new(points, curve.style))
+            .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+    }
+    if spec.fit_annotate
+    {
+        draw_fit_annotations(grid, spec, series)?;
+    }
+
+    if let Some(legend) = legend
+    {
+        let position = match spec.legend_position
+        {
+            LegendPosition::UpperLeft => SeriesLabelPosition::UpperLeft,
+            LegendPosition::LowerLeft => SeriesLabelPosition::LowerLeft,
+            LegendPosition::LowerRight => SeriesLabelPosition::LowerRight,
+            LegendPosition::UpperRight | LegendPosition::Outside => SeriesLabelPosition::UpperRight,
+        };
+        draw_legend(grid, spec, legend, position)?;
+    }
+    Ok(())
+}
+
+/// Writes formula and R² of each fitted curve in its color into a corner left of the legend
+fn draw_fit_annotations<'a, DB, X, Y>(
+    grid: &ChartContext<'a, DB, Cartesian2d<X, Y>>,
+    spec: &PlotSpec,
+    series: &[PlotSeries],
+) -> Result<(), PlotError>
+where
+    DB: DrawingBackend + 'a,
+    X: Ranged<ValueType = f64>,
+    Y: Ranged<ValueType = f64>,
+{
+    let lines: Vec<(String, ShapeStyle)> = series
+        .iter()
+        .flat_map(|s| &s.fits)
+        .map(|curve| (format!("{}: {}", curve.label, curve.fitted.describe()), curve.style))
+        .collect();
+    let area = grid.plotting_area().strip_coord_spec();
+    let (_, height) = area.dim_in_pixel();
+    let line_height = (spec.label_font_size * 13 / 10) as i32;
+    // the upper left corner unless the legend is there
+    let top = if spec.legend_position == LegendPosition::UpperLeft
+    {
+        height as i32 - 10 - line_height * lines.len() as i32
+    }
+    else
+    {
+        10
+    };
+    for (i, (text, style)) in lines.iter().enumerate()
+    {
+        let text_style =
+            TextStyle::from((spec.label_font.as_str(), spec.label_font_size).into_font())
+                .color(&style.color);
+        area.draw_text(text, &text_style, (10, top + i as i32 * line_height))
+            .map_err(|e| PlotError::InvalidData(format!("Draw error: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Bold key points of the X axis that get a rotated label
+const X_LABELS: usize = 10;
+/// Gap in pixels between the X axis and the end of a rotated label
+const ROTATED_LABEL_GAP: u32 = 10;
+
+/// Text of the X label at `x`
+fn x_label<X>(spec: &PlotSpec, x_axis: &X, x: &f64) -> String
+where
+    X: ValueFormatter<f64>,
+{
+    if spec.si_format_x
+    {
+        format_si_number(*x)
+    }
+    else
+    {
+        x_axis.format_ext(x)
+    }
+}
+
+/// Height of an X label area holding `labels` rotated by 90 degrees above the axis
+/// description, at least `xdesc_area`
+fn rotated_x_label_area(spec: &PlotSpec, labels: &[String]) -> u32
+{
+    let font = (spec.label_font.as_str(), spec.label_font_size).into_font();
+    // turned upright, the widest label reaches furthest down
+    let extent = labels
+        .iter()
+        .map(|label| {
+            font.box_size(label)
+                .map(|(width, _)| width)
+                .unwrap_or(label.chars().count() as u32 * spec.label_font_size * 3 / 5)
+        })
+        .max()
+        .unwrap_or(0);
+    let description = match spec.xdesc.as_deref()
+    {
+        Some(xdesc) if !xdesc.is_empty() => spec.axis_desc_font_size * 3 / 2,
+        _ => 0,
+    };
+    // plotters draws the axis description at the bottom of the label area
+    (ROTATED_LABEL_GAP + extent + ROTATED_LABEL_GAP / 2 + description).max(spec.xdesc_area)
+}
+
+/// Draws the X labels at the bold key points, ending just below the axis
+fn draw_rotated_x_labels<'a, DB, X, Y>(
+    grid: &ChartContext<'a, DB, Cartesian2d<X, Y>>,
+    spec: &PlotSpec,
+    style: TextStyle,
+) -> Result<(), PlotError>
+where
+    DB: DrawingBackend + 'a,
+    X: Ranged<ValueType = f64> + ValueFormatter<f64>,
+    Y: Ranged<ValueType = f64> + ValueFormatter<f64>,
+{
+    let x_axis = grid.as_coord_spec().x_spec();
+    let y_bottom = grid.y_range().start;
+    let area = grid.plotting_area().strip_coord_spec();
+    let (base_x, base_y) = area.get_base_pixel();
+    for x in x_axis.key_points(BoldPoints(X_LABELS))
+    {
+        let label = x_label(spec, x_axis, &x);
+        let (px, py) = grid.backend_coord(&(x, y_bottom));
+        area.draw_text(&label, &style, (px - base_x, py - base_y + ROTATED_LABEL_GAP as i32))
+            .map_err(|e| PlotError::InvalidData(format!("Draw error: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Adds the entries as series without data and draws them with configure_series_labels
+fn draw_legend<'a, DB, CT>(
+    chart: &mut ChartContext<'a, DB, CT>,
+    spec: &PlotSpec,
+    legend: &[LegendEntry],
+    position: SeriesLabelPosition,
+) -> Result<(), PlotError>
+where
+    DB: DrawingBackend + 'a,
+    CT: CoordTranslate,
+{
+    for (label, style) in legend
+    {
+        let anno = chart
+            .draw_series(std::iter::empty::<EmptyElement<CT::From, DB>>())
+            .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?
+            .label(label);
+        legend_swatch(anno, spec, *style);
+    }
+    chart
+        .configure_series_labels()
+        .position(position)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .label_font((spec.label_font.as_str(), spec.label_font_size))
+        .draw()
+        .map_err(|e| PlotError::InvalidData(format!("Draw error: {}", e)))?;
+    Ok(())
+}
+
+/// Legend symbol matching the plotted shape
+fn legend_swatch<'a, DB>(anno: &mut SeriesAnno<'a, DB>, spec: &PlotSpec, style: ShapeStyle)
+where
+    DB: DrawingBackend + 'a,
+{
+    let point_size = spec.point_size.max(6);
+    let line_width = spec.line_width;
+    match spec.shape
+    {
+        Shape::Column | Shape::Hexbin | Shape::Heat2d =>
+        {
+            anno.legend(move |(x, y)| Rectangle::new([(x - 8, y - 8), (x + 8, y + 8)], style))
+        }
+        Shape::Circle => anno.legend(move |(x, y)| Circle::new((x, y), point_size, style)),
+        Shape::Line | Shape::LinePoints => anno.legend(move |(x, y)| {
+            PathElement::new(vec![(x - 10, y), (x + 10, y)], style.stroke_width(line_width))
+        }),
+    };
+}
+
+/// Space needed right of the plotting area for a legend with these labels
+fn outside_legend_width(spec: &PlotSpec, legend: &[LegendEntry]) -> u32
+{
+    let longest = legend
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0) as u32;
+    // rough average glyph width, plus swatch and padding
+    longest * spec.label_font_size * 6 / 10 + 80
+}
+
+/// Width of the color bar including its labels
+fn colorbar_width(spec: &PlotSpec) -> u32
+{
+    40 + 6 * spec.label_font_size + 26
+}
+
+/// Draws the gradient as a vertical bar with value labels, titled with the gradient column name
+fn draw_colorbar<DB>(
+    area: &DrawingArea<DB, Shift>,
+    spec: &PlotSpec,
+    name: &str,
+    scale: &GradientScale,
+    x_label_area: u32,
+) -> Result<(), PlotError>
+where
+    DB: DrawingBackend,
+{
+    // the bar runs over relative gradient positions, values are placed via the scale
+    let mut bar = ChartBuilder::on(area)
+        .margin_top(26u32)
+        .margin_bottom(26 + x_label_area)
+        .margin_right(26u32)
+        .right_y_label_area_size(6 * spec.label_font_size)
+        .caption(name, (spec.axis_desc_font.as_str(), spec.axis_desc_font_size))
+        .build_cartesian_2d(0f64..1f64, 0f64..1f64)
+        .map_err(|e| PlotError::InvalidData(format!("Grid creation error: {}", e)))?;
+
+    bar.configure_mesh()
+        .disable_mesh()
+        .disable_x_axis()
+        .y_labels(0)
+        .draw()
+        .map_err(|e| PlotError::InvalidData(format!("Draw error: {}", e)))?;
+
+    let steps = 256;
+    let step = 1.0 / steps as f64;
+    bar.draw_series((0..steps).map(|i| {
+        let from = i as f64 * step;
+        let color = scale.color_at_position(from + step / 2.0, 1.0);
+        Rectangle::new([(0.0, from), (1.0, from + step)], color.filled())
+    }))
+    .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+
+    // min and max at the ends, nice ticks in between unless too close to them
+    let format = |v: f64| {
+        if spec.si_format_gradient
+        {
+            format_si_number(v)
+        }
+        else
+        {
+            format_number(v)
+        }
+    };
+    let (min, max) = (scale.min, scale.max);
+    let ticks = if min < max
+    {
+        let mut ticks = vec![min, max];
+        ticks.extend(
+            colorbar_ticks(min, max, scale.log)
+                .into_iter()
+                .filter(|&tick| {
+                    let t = scale.position(tick);
+                    t > 0.05 && t < 0.95
+                }),
+        );
+        ticks
+    }
+    else
+    {
+        // a constant gradient column still gets a readable bar
+        vec![min]
+    };
+
+    let (base_x, base_y) = area.get_base_pixel();
+    let label_style = TextStyle::from((spec.label_font.as_str(), spec.label_font_size).into_font())
+        .pos(Pos::new(HPos::Left, VPos::Center));
+    for tick in ticks
+    {
+        let t = if min < max { scale.position(tick) } else { 0.5 };
+        let (x, y) = bar.backend_coord(&(1.0, t));
+        let (x, y) = (x - base_x, y - base_y);
+        area.draw(&PathElement::new(vec![(x, y), (x + 6, y)], BLACK))
+            .map_err(|e| PlotError::InvalidData(format!("Draw error: {}", e)))?;
+        area.draw(&Text::new(format(tick), (x + 10, y), label_style.clone()))
+            .map_err(|e| PlotError::InvalidData(format!("Draw error: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Nice ticks strictly inside min..max, powers of ten on a logarithmic scale spanning a decade
+fn colorbar_ticks(min: f64, max: f64, log: bool) -> Vec<f64>
+{
+    let mut ticks = Vec::new();
+    if log && max / min >= 10.0
+    {
+        let mut tick = 10f64.powf(min.log10().ceil());
+        while tick < max
+        {
+            if tick > min
+            {
+                ticks.push(tick);
+            }
+            tick *= 10.0;
+        }
+    }
+    else
+    {
+        let tick_step = nice_step(max - min, 5);
+        let mut tick = (min / tick_step).ceil() * tick_step;
+        while tick < max
+        {
+            if tick > min
+            {
+                ticks.push(tick);
+            }
+            tick += tick_step;
+        }
+    }
+    ticks
+}
+
+fn draw_columns<'a, DB, X, Y>(
+    grid: &mut ChartContext<'a, DB, Cartesian2d<X, Y>>,
+    s: &PlotSeries,
+) -> Result<(), PlotError>
+where
+    DB: DrawingBackend + 'a,
+    X: Ranged<ValueType = f64>,
+    Y: Ranged<ValueType = f64>,
+{
+    if let Some(columns) = &s.columns
+    {
+        grid.draw_series(s.xyc()?.zip(columns).filter_map(
+            |(((_, y), c), &(left, right, base))| {
+                y.map(|y| Rectangle::new([(left, y), (right, base)], c))
+            },
+        ))
+        .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+        return Ok(());
+    }
+    let half_width = column_half_width(&s.x)?;
+    grid.draw_series(s.xyc()?.filter_map(|((x, y), c)| match (x, y)
+    {
+        (Some(xx), Some(yy)) =>
+        {
+            Some(Rectangle::new([(xx - half_width, yy), (xx + half_width, 0f64)], c))
+        }
+        _ => None,
+    }))
+    .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+    Ok(())
+}
+
+/// Half the width of columns centered on their X value, 0.4 of the smallest distance between
+/// X values, e.g. of slots or of seconds on a time axis, and 0.4 for a single X value
+fn column_half_width(x: &Series) -> Result<f64, PlotError>
+{
+    let mut values: Vec<f64> = x
+        .f64()
+        .map_err(|_| PlotError::InvalidData("X column is not numeric".to_string()))?
+        .into_iter()
+        .flatten()
+        .filter(|x| x.is_finite())
+        .collect();
+    values.sort_by(f64::total_cmp);
+    let spacing = values
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .filter(|&distance| distance > 0.0)
+        .min_by(f64::total_cmp);
+    Ok(0.4 * spacing.unwrap_or(1.0))
+}
+
+/// Draws the occupied density cells in their count color
+fn draw_cells<'a, DB, X, Y>(
+    grid: &mut ChartContext<'a, DB, Cartesian2d<X, Y>>,
+    s: &PlotSeries,
+) -> Result<(), PlotError>
+where
+    DB: DrawingBackend + 'a,
+    X: Ranged<ValueType = f64>,
+    Y: Ranged<ValueType = f64>,
+{
+    grid.draw_series(
+        s.cells
+            .iter()
+            .map(|(cell, style)| Polygon::new(cell.outline.clone(), *style)),
+    )
+    .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+    Ok(())
+}
+
+/// Draws a circle per point, missing values are left out
+fn draw_points<'a, DB, X, Y>(
+    grid: &mut ChartContext<'a, DB, Cartesian2d<X, Y>>,
+    spec: &PlotSpec,
+    s: &PlotSeries,
+) -> Result<(), PlotError>
+where
+    DB: DrawingBackend + 'a,
+    X: Ranged<ValueType = f64>,
+    Y: Ranged<ValueType = f64>,
+{
+    let point_size = spec.point_size;
+    grid.draw_series(s.xyc()?.filter_map(|((x, y), c)| match (x, y)
+    {
+        (Some(xx), Some(yy)) => Some(Circle::new((xx, yy), point_size, c)),
+        _ => None,
+    }))
+    .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+    Ok(())
+}
+
+/// Draws error bars from the lower to the upper bounds of each point,
+/// vertical ones only without lines as those get a band
+fn draw_error_bars<'a, DB, X, Y>(
+    grid: &mut ChartContext<'a, DB, Cartesian2d<X, Y>>,
+    spec: &PlotSpec,
+    s: &PlotSeries,
+) -> Result<(), PlotError>
+where
+    DB: DrawingBackend + 'a,
+    X: Ranged<ValueType = f64>,
+    Y: Ranged<ValueType = f64>,
+{
+    let whisker = spec.point_size * 2 + 4;
+    // opaque, translucent columns would hide them
+    let bar_style = |c: ShapeStyle| {
+        RGBAColor(c.color.0, c.color.1, c.color.2, 1.0).stroke_width(spec.line_width)
+    };
+    if let Some(bounds) = s.y_bounds.as_ref().filter(|_| !spec.shape.has_line())
+    {
+        grid.draw_series(s.xyc()?.zip(bound_values(bounds)?).filter_map(
+            |(((x, y), c), bounds)| match (x, y, bounds)
+            {
+                (Some(x), Some(y), (Some(low), Some(high))) =>
+                {
+                    Some(ErrorBar::new_vertical(x, low, y, high, bar_style(c), whisker))
+                }
+                _ => None,
+            },
+        ))
+        .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+    }
+    if let Some(bounds) = &s.x_bounds
+    {
+        grid.draw_series(s.xyc()?.zip(bound_values(bounds)?).filter_map(
+            |(((x, y), c), bounds)| match (x, y, bounds)
+            {
+                (Some(x), Some(y), (Some(low), Some(high))) =>
+                {
+                    Some(ErrorBar::new_horizontal(y, low, x, high, bar_style(c), whisker))
+                }
+                _ => None,
+            },
+        ))
+        .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Draws the series as lines, broken at missing values
+fn draw_lines<'a, DB, X, Y>(
+    grid: &mut ChartContext<'a, DB, Cartesian2d<X, Y>>,
+    spec: &PlotSpec,
+    s: &PlotSeries,
+) -> Result<(), PlotError>
+where
+    DB: DrawingBackend + 'a,
+    X: Ranged<ValueType = f64>,
+    Y: Ranged<ValueType = f64>,
+{
+    for (points, style) in s.polylines(spec)?
+    {
+        grid.draw_series(LineSeries::new(points, style))
+            .map_err(|e| PlotError::InvalidData(format!("Backend Error: {}", e)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::fit::Fit;
+
+    fn values(series: &Series) -> Vec<Option<f64>>
+    {
+        series.f64().unwrap().into_iter().collect()
+    }
+
+    #[test]
+    fn unstable_fits_are_reported()
+    {
+        let df = df!("x" => [0.0, 1.0, 2.0, 3.0, 4.0, 1e3], "y" => [0.0, 1.0, 2.0, 3.0, 4.0, 3.0])
+            .unwrap();
+        let mut svg = String::new();
+        let spec = PlotSpec::default().x(1).y(vec![2]).fit(Fit::Poly(5));
+        let report =
+            plot_on_backend(&spec, &df, SVGBackend::with_string(&mut svg, (400, 300))).unwrap();
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].starts_with("no fit for y: "), "{:?}", report.warnings);
+
+        let spec = spec.fit(Fit::Linear);
+        let report =
+            plot_on_backend(&spec, &df, SVGBackend::with_string(&mut svg, (400, 300))).unwrap();
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn rotated_label_areas()
+    {
+        let spec = PlotSpec::default().xdesc_area(0);
+        let labels = |labels: &[&str]| {
+            let labels: Vec<String> = labels.iter().map(|label| label.to_string()).collect();
+            rotated_x_label_area(&spec, &labels)
+        };
+        // the widest label decides
+        let long = labels(&["a much longer label"]);
+        assert!(long > labels(&["a"]));
+        assert_eq!(labels(&["a", "a much longer label", "b"]), long);
+        assert_eq!(labels(&[]), ROTATED_LABEL_GAP * 3 / 2);
+        // room for the axis description below the labels
+        assert!(
+            rotated_x_label_area(&spec.clone().xdesc("x"), &["a".to_string()]) > labels(&["a"])
+        );
+    }
+
+    #[test]
+    fn error_bounds_from_columns()
+    {
+        let df = df!(
+            "y" => [Some(1.0), Some(2.0), None, Some(4.0)],
+            "err" => [Some(0.5), Some(-1.0), Some(1.0), None],
+            "lo" => [0, 1, 1, 3],
+            "hi" => [Some(2), None, Some(3), Some(5)],
+        )
+        .unwrap();
+        let y = df.column("y").unwrap().as_materialized_series();
+
+        // distances count in both directions, whatever their sign
+        let (low, high) = error_bounds(&df, y, ErrorColumns::Symmetric(2), None, "Y").unwrap();
+        assert_eq!(values(&low), [Some(0.5), Some(1.0), None, None]);
+        assert_eq!(values(&high), [Some(1.5), Some(3.0), None, None]);
+
+        // a missing end column ends at the value itself
+        let range = ErrorColumns::Range {
+            low: Some(3),
+            high: None,
+        };
+        let (low, high) = error_bounds(&df, y, range, None, "Y").unwrap();
+        assert_eq!(values(&low), [Some(0.0), Some(1.0), Some(1.0), Some(3.0)]);
+        assert_eq!(values(&high), [Some(1.0), Some(2.0), None, Some(4.0)]);
+        let range = ErrorColumns::Range {
+            low: None,
+            high: Some(4),
+        };
+        let (low, high) = error_bounds(&df, y, range, None, "Y").unwrap();
+        assert_eq!(values(&low), values(y));
+        assert_eq!(values(&high), [Some(2.0), None, Some(3.0), Some(5.0)]);
+
+        assert!(error_bounds(&df, y, ErrorColumns::Symmetric(5), None, "Y").is_err());
+    }
+
+    #[test]
+    fn column_widths()
+    {
+        let half_width = |x: &[f64]| column_half_width(&Series::new("x".into(), x)).unwrap();
+        assert_eq!(half_width(&[0.0, 1.0, 2.0]), 0.4);
+        assert_eq!(half_width(&[5.0, 0.0, 10.0, 10.0]), 2.0);
+        assert_eq!(half_width(&[3.0]), 0.4);
+        // hourly points on a time axis
+        assert_eq!(half_width(&[1.7e9, 1.7e9 + 3600.0, 1.7e9 + 7200.0]), 1440.0);
+    }
+
+    #[test]
+    fn error_bounds_on_a_time_axis()
+    {
+        let df = df!(
+            "t" => ["2024-01-01 00:01:00", "2024-01-01 00:02:00"],
+            "from" => ["2024-01-01 00:00:30", "2024-01-01 00:01:00"],
+            "seconds" => [10, 20],
+        )
+        .unwrap();
+        let parse = TimeParse::Auto;
+        let t =
+            time::epoch_seconds(df.column("t").unwrap().as_materialized_series(), &parse).unwrap();
+        let start = values(&t)[0].unwrap();
+
+        let range = ErrorColumns::Range {
+            low: Some(2),
+            high: None,
+        };
+        let (low, _) = error_bounds(&df, &t, range, Some(&parse), "X").unwrap();
+        assert_eq!(values(&low), [Some(start - 30.0), Some(start)]);
+        // distances are in seconds
+        let (low, high) =
+            error_bounds(&df, &t, ErrorColumns::Symmetric(3), Some(&parse), "X").unwrap();
+        assert_eq!(values(&low), [Some(start - 10.0), Some(start + 40.0)]);
+        assert_eq!(values(&high), [Some(start + 10.0), Some(start + 80.0)]);
+    }
+}
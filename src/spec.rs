@@ -0,0 +1,391 @@
+use crate::aggregate::{Aggregate, ErrorBars};
+use crate::axis::AxisRange;
+use crate::category::XOrder;
+use crate::color::{CategoryOrder, GradientScheme, Palette};
+use crate::fit::Fit;
+use crate::hist::Histogram;
+use crate::panel::FacetScales;
+use crate::time::TimeParse;
+use plotters::style::RGBColor;
+use std::str::FromStr;
+
+/// Shape drawn for each data point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shape
+{
+    #[default]
+    Circle,
+    Column,
+    /// Connects the points, broken at missing values
+    Line,
+    /// Line with a circle on each point
+    LinePoints,
+    /// Hexagonal cells colored by the number of points in them
+    Hexbin,
+    /// Rectangular cells colored by the number of points in them
+    Heat2d,
+}
+
+impl Shape
+{
+    pub fn has_line(self) -> bool
+    {
+        matches!(self, Shape::Line | Shape::LinePoints)
+    }
+
+    /// Bins the points into cells instead of drawing each one
+    pub fn is_density(self) -> bool
+    {
+        matches!(self, Shape::Hexbin | Shape::Heat2d)
+    }
+}
+
+impl FromStr for Shape
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "circle" => Ok(Shape::Circle),
+            "column" => Ok(Shape::Column),
+            "line" => Ok(Shape::Line),
+            "line+points" => Ok(Shape::LinePoints),
+            "hexbin" => Ok(Shape::Hexbin),
+            "heat2d" => Ok(Shape::Heat2d),
+            _ => Err(format!(
+                "unknown shape {}, expected circle, column, line, line+points, hexbin or heat2d",
+                s
+            )),
+        }
+    }
+}
+
+/// Where the legend is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LegendPosition
+{
+    UpperLeft,
+    #[default]
+    UpperRight,
+    LowerLeft,
+    LowerRight,
+    /// right of the plotting area
+    Outside,
+}
+
+impl FromStr for LegendPosition
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "upper-left" => Ok(LegendPosition::UpperLeft),
+            "upper-right" => Ok(LegendPosition::UpperRight),
+            "lower-left" => Ok(LegendPosition::LowerLeft),
+            "lower-right" => Ok(LegendPosition::LowerRight),
+            "outside" => Ok(LegendPosition::Outside),
+            _ => Err(format!(
+                "unknown legend position {}, expected upper-left, upper-right, lower-left, lower-right or outside",
+                s
+            )),
+        }
+    }
+}
+
+/// Columns giving the uncertainty of each value, 1-based like all column indices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorColumns
+{
+    /// distance to both ends
+    Symmetric(usize),
+    /// absolute lower and upper ends, a missing one ends at the value itself
+    Range
+    {
+        low: Option<usize>,
+        high: Option<usize>,
+    },
+}
+
+/// Everything needed to render a DataFrame into a chart.
+///
+/// Column indices are 1-based, an X index of 0 plots against the row number.
+/// Each Y column becomes its own series, several series or a color facet get a legend.
+/// Start from `PlotSpec::default()` and chain the setters:
+///
+/// ```no_run
+/// use plotxy::{PlotSpec, Shape};
+/// let spec = PlotSpec::default().x(1).y(vec![3, 4]).shape(Shape::Column).title("runtime");
+/// ```
+#[derive(Debug, Clone)]
+pub struct PlotSpec
+{
+    pub x: usize,
+    pub y: Vec<usize>,
+    pub color: Option<usize>,
+    pub gradient: Option<usize>,
+    /// one panel per category of this column
+    pub facet: Option<usize>,
+    /// panel grid, default about square
+    pub facet_rows: Option<usize>,
+    pub facet_columns: Option<usize>,
+    pub facet_scales: FacetScales,
+    /// colors of several series and color facet categories
+    pub palette: Palette,
+    pub color_order: CategoryOrder,
+    /// fixed colors for color facet categories, taking precedence over the palette
+    pub color_map: Vec<(String, RGBColor)>,
+    pub gradient_scheme: GradientScheme,
+    /// pin the gradient domain, e.g. to compare several plots, default data range
+    pub gradient_min: Option<f64>,
+    pub gradient_max: Option<f64>,
+    pub gradient_log: bool,
+    pub alpha: f64,
+    pub plot_color: RGBColor,
+    pub logx: bool,
+    pub logy: bool,
+    /// treat X as points in time and label it with dates
+    pub x_time: Option<TimeParse>,
+    /// strftime format for time axis labels, default depends on the time span
+    pub time_format: Option<String>,
+    /// one slot per distinct X value, string X columns default to input order
+    pub x_categories: Option<XOrder>,
+    pub rotate_x_labels: bool,
+    /// axis limits, default derived from the data by `x_range` and `y_range`
+    pub x_dim_min: Option<f64>,
+    pub x_dim_max: Option<f64>,
+    pub y_dim_min: Option<f64>,
+    pub y_dim_max: Option<f64>,
+    pub x_range: AxisRange,
+    pub y_range: AxisRange,
+    pub title: Option<String>,
+    pub width: u32,
+    /// image height, of each row of panels with a facet
+    pub height: u32,
+    /// axis descriptions, default the X header name and the Y header names
+    pub xdesc: Option<String>,
+    pub ydesc: Option<String>,
+    pub xdesc_area: u32,
+    pub ydesc_area: u32,
+    pub label_font: String,
+    pub label_font_size: u32,
+    pub axis_desc_font: String,
+    pub axis_desc_font_size: u32,
+    pub title_font: String,
+    pub title_font_size: u32,
+    pub point_size: u32,
+    pub shape: Shape,
+    /// density cells along the X range, unless `cell_width` is given
+    pub cells: usize,
+    /// density cell size in data units, decades on a logarithmic axis
+    pub cell_width: Option<f64>,
+    pub cell_height: Option<f64>,
+    /// bin the X column and plot the counts per bin instead of X/Y points
+    pub hist: Option<Histogram>,
+    /// reduce the Y values of each X value, and color facet category, to one
+    pub aggregate: Option<Aggregate>,
+    /// error bars around aggregated values
+    pub error_bars: Option<ErrorBars>,
+    /// error bars along X
+    pub x_error: Option<ErrorColumns>,
+    /// error bars along Y, a shaded band for line shapes
+    pub y_error: Option<ErrorColumns>,
+    /// curve fitted through each series, per color facet category if given
+    pub fit: Option<Fit>,
+    /// print the fitted formulas and R² to stdout
+    pub fit_print: bool,
+    /// write the fitted formulas and R² onto the chart
+    pub fit_annotate: bool,
+    /// connect line points in X order instead of input order
+    pub sort_x: bool,
+    pub line_width: u32,
+    pub si_format_x: bool,
+    pub si_format_y: bool,
+    /// use SI number formatting for the color bar of a gradient
+    pub si_format_gradient: bool,
+    /// draw a legend for several series and color facet categories, a color bar for a gradient
+    pub legend: bool,
+    pub legend_position: LegendPosition,
+}
+
+impl Default for PlotSpec
+{
+    fn default() -> Self
+    {
+        PlotSpec {
+            x: 1,
+            y: vec![2],
+            color: None,
+            gradient: None,
+            facet: None,
+            facet_rows: None,
+            facet_columns: None,
+            facet_scales: FacetScales::Shared,
+            palette: Palette::default(),
+            color_order: CategoryOrder::default(),
+            color_map: Vec::new(),
+            gradient_scheme: GradientScheme::default(),
+            gradient_min: None,
+            gradient_max: None,
+            gradient_log: false,
+            alpha: 0.3,
+            plot_color: RGBColor(0x1E, 0x88, 0xE5),
+            logx: false,
+            logy: false,
+            x_time: None,
+            time_format: None,
+            x_categories: None,
+            rotate_x_labels: false,
+            x_dim_min: None,
+            x_dim_max: None,
+            y_dim_min: None,
+            y_dim_max: None,
+            x_range: AxisRange::Auto,
+            y_range: AxisRange::Auto,
+            title: None,
+            width: 2560,
+            height: 1200,
+            xdesc: None,
+            ydesc: None,
+            xdesc_area: 70,
+            ydesc_area: 100,
+            label_font: "sans-serif".to_string(),
+            label_font_size: 24,
+            axis_desc_font: "sans-serif".to_string(),
+            axis_desc_font_size: 22,
+            title_font: "sans-serif".to_string(),
+            title_font_size: 24,
+            point_size: 3,
+            shape: Shape::Circle,
+            cells: 50,
+            cell_width: None,
+            cell_height: None,
+            hist: None,
+            aggregate: None,
+            error_bars: None,
+            x_error: None,
+            y_error: None,
+            fit: None,
+            fit_print: false,
+            fit_annotate: false,
+            sort_x: false,
+            line_width: 2,
+            si_format_x: false,
+            si_format_y: false,
+            si_format_gradient: false,
+            legend: true,
+            legend_position: LegendPosition::UpperRight,
+        }
+    }
+}
+
+// Generates chaining setters named like the fields, optional fields take the bare value
+macro_rules! setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: $ty) -> Self
+            {
+                self.$field = $field;
+                self
+            }
+        )*
+    };
+    (optional $($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: $ty) -> Self
+            {
+                self.$field = Some($field);
+                self
+            }
+        )*
+    };
+    (optional into $($field:ident),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: impl Into<String>) -> Self
+            {
+                self.$field = Some($field.into());
+                self
+            }
+        )*
+    };
+    (into $($field:ident),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: impl Into<String>) -> Self
+            {
+                self.$field = $field.into();
+                self
+            }
+        )*
+    };
+}
+
+impl PlotSpec
+{
+    setters!(
+        x: usize,
+        y: Vec<usize>,
+        alpha: f64,
+        plot_color: RGBColor,
+        palette: Palette,
+        facet_scales: FacetScales,
+        color_order: CategoryOrder,
+        color_map: Vec<(String, RGBColor)>,
+        gradient_scheme: GradientScheme,
+        gradient_log: bool,
+        logx: bool,
+        logy: bool,
+        rotate_x_labels: bool,
+        x_range: AxisRange,
+        y_range: AxisRange,
+        width: u32,
+        height: u32,
+        xdesc_area: u32,
+        ydesc_area: u32,
+        label_font_size: u32,
+        axis_desc_font_size: u32,
+        title_font_size: u32,
+        point_size: u32,
+        shape: Shape,
+        cells: usize,
+        fit_print: bool,
+        fit_annotate: bool,
+        sort_x: bool,
+        line_width: u32,
+        si_format_x: bool,
+        si_format_y: bool,
+        si_format_gradient: bool,
+        legend: bool,
+        legend_position: LegendPosition,
+    );
+
+    setters!(
+        optional color: usize,
+        gradient: usize,
+        facet: usize,
+        facet_rows: usize,
+        facet_columns: usize,
+        gradient_min: f64,
+        gradient_max: f64,
+        x_time: TimeParse,
+        x_categories: XOrder,
+        hist: Histogram,
+        aggregate: Aggregate,
+        error_bars: ErrorBars,
+        x_error: ErrorColumns,
+        y_error: ErrorColumns,
+        fit: Fit,
+        cell_width: f64,
+        cell_height: f64,
+        x_dim_min: f64,
+        x_dim_max: f64,
+        y_dim_min: f64,
+        y_dim_max: f64,
+    );
+
+    setters!(optional into title, xdesc, ydesc, time_format);
+
+    setters!(into label_font, axis_desc_font, title_font);
+}
@@ -0,0 +1,378 @@
+//! Date and time X axes.
+//!
+//! Time values are carried as seconds since the Unix epoch in UTC, so they plot like any
+//! other number. Only the axis knows about calendars, its ticks come from plotters'
+//! `RangedDateTime`.
+
+use crate::PlotError;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use plotters::coord::ranged1d::{KeyPointHint, NoDefaultFormatting, Ranged, ValueFormatter};
+use plotters::coord::types::{RangedCoordf64, RangedDateTime};
+use polars::prelude::*;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// How the X column is turned into points in time
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum TimeParse
+{
+    /// datetime columns, ISO 8601 like strings or epoch numbers guessed by magnitude
+    #[default]
+    Auto,
+    /// strftime format like `%d/%m/%Y %H:%M`
+    Format(String),
+    EpochSeconds,
+    EpochMillis,
+    EpochNanos,
+}
+
+impl FromStr for TimeParse
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "auto" => Ok(TimeParse::Auto),
+            "epoch-s" => Ok(TimeParse::EpochSeconds),
+            "epoch-ms" => Ok(TimeParse::EpochMillis),
+            "epoch-ns" => Ok(TimeParse::EpochNanos),
+            _ if s.contains('%') => Ok(TimeParse::Format(s.to_string())),
+            _ => Err(format!(
+                "unknown time parsing {}, expected auto, epoch-s, epoch-ms, epoch-ns or a strftime format",
+                s
+            )),
+        }
+    }
+}
+
+// tried in order on the first value in auto mode
+const AUTO_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%Y/%m/%d %H:%M",
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%d.%m.%Y %H:%M:%S",
+    "%d.%m.%Y",
+];
+
+/// Seconds since the epoch for each row of `series`, values that do not parse become null
+pub fn epoch_seconds(series: &Series, parse: &TimeParse) -> Result<Series, PlotError>
+{
+    let name = series.name().clone();
+    let seconds: Float64Chunked = match (series.dtype(), parse)
+    {
+        (DataType::Datetime(unit, _), _) =>
+        {
+            let per_second = match unit
+            {
+                TimeUnit::Nanoseconds => 1e9,
+                TimeUnit::Microseconds => 1e6,
+                TimeUnit::Milliseconds => 1e3,
+            };
+            scaled(series, 1.0 / per_second)?
+        }
+        (DataType::Date, _) => scaled(series, 86400.0)?,
+        (_, TimeParse::EpochSeconds) => scaled(series, 1.0)?,
+        (_, TimeParse::EpochMillis) => scaled(series, 1e-3)?,
+        (_, TimeParse::EpochNanos) => scaled(series, 1e-9)?,
+        (DataType::String, TimeParse::Auto) =>
+        {
+            let strings = series.str()?;
+            let first = strings
+                .into_iter()
+                .flatten()
+                .next()
+                .ok_or_else(|| PlotError::InvalidData("No data in time column".to_string()))?;
+            if DateTime::parse_from_rfc3339(first).is_ok()
+            {
+                strings
+                    .into_iter()
+                    .map(|s| {
+                        s.and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                            .map(|t| nanos_to_seconds(t.timestamp_nanos_opt()))
+                    })
+                    .collect()
+            }
+            else
+            {
+                let format = AUTO_FORMATS
+                    .iter()
+                    .find(|format| parse_with_format(first, format).is_some())
+                    .ok_or_else(|| {
+                        PlotError::InvalidData(format!(
+                            "Cannot detect the time format of {}, use --x-time with a strftime format",
+                            first
+                        ))
+                    })?;
+                parse_strings(strings, format)
+            }
+        }
+        (dtype, TimeParse::Auto) if dtype.is_primitive_numeric() =>
+        {
+            // guess the unit from the magnitude, seconds stay below 1e11 until the year 5000
+            let largest = scaled(series, 1.0)?
+                .into_iter()
+                .flatten()
+                .map(f64::abs)
+                .fold(0.0, f64::max);
+            let factor = if largest >= 1e17
+            {
+                1e-9
+            }
+            else if largest >= 1e14
+            {
+                1e-6
+            }
+            else if largest >= 1e11
+            {
+                1e-3
+            }
+            else
+            {
+                1.0
+            };
+            scaled(series, factor)?
+        }
+        (_, TimeParse::Format(format)) =>
+        {
+            let strings = series.cast(&DataType::String)?;
+            parse_strings(strings.str()?, format)
+        }
+        (dtype, TimeParse::Auto) =>
+        {
+            return Err(PlotError::InvalidData(format!(
+                "Cannot use a column of type {} as time",
+                dtype
+            )))
+        }
+    };
+    if seconds.null_count() == seconds.len()
+    {
+        return Err(PlotError::InvalidData(
+            "No value of the time column could be parsed".to_string(),
+        ));
+    }
+    Ok(seconds.with_name(name).into_series())
+}
+
+fn scaled(series: &Series, factor: f64) -> Result<Float64Chunked, PlotError>
+{
+    let values = series.cast(&DataType::Float64)?;
+    Ok(values
+        .f64()
+        .map_err(|_| PlotError::InvalidData("Time column is not numeric".to_string()))?
+        * factor)
+}
+
+fn parse_strings(strings: &StringChunked, format: &str) -> Float64Chunked
+{
+    strings
+        .into_iter()
+        .map(|s| s.and_then(|s| parse_with_format(s, format)))
+        .collect()
+}
+
+/// Parses a date and time, a date only or a time with offset
+fn parse_with_format(value: &str, format: &str) -> Option<f64>
+{
+    let value = value.trim();
+    if let Ok(t) = NaiveDateTime::parse_from_str(value, format)
+    {
+        return Some(nanos_to_seconds(t.and_utc().timestamp_nanos_opt()));
+    }
+    if let Ok(t) = DateTime::parse_from_str(value, format)
+    {
+        return Some(nanos_to_seconds(t.timestamp_nanos_opt()));
+    }
+    NaiveDate::parse_from_str(value, format)
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|t| t.and_utc().timestamp() as f64)
+}
+
+fn nanos_to_seconds(nanos: Option<i64>) -> f64
+{
+    nanos.map(|n| n as f64 / 1e9).unwrap_or(f64::NAN)
+}
+
+/// Checks a strftime format for label output, chrono panics on invalid ones while formatting
+pub fn check_format(format: &str) -> Result<(), PlotError>
+{
+    if chrono::format::StrftimeItems::new(format).any(|item| item == chrono::format::Item::Error)
+    {
+        return Err(PlotError::InvalidData(format!("Invalid time format {}", format)));
+    }
+    Ok(())
+}
+
+fn to_datetime(seconds: f64) -> NaiveDateTime
+{
+    DateTime::from_timestamp(seconds.floor() as i64, ((seconds - seconds.floor()) * 1e9) as u32)
+        .unwrap_or_default()
+        .naive_utc()
+}
+
+/// Tick label format fitting the span of the axis
+fn default_format(span: f64) -> &'static str
+{
+    const DAY: f64 = 86400.0;
+    if span > 2.0 * 365.0 * DAY
+    {
+        "%Y-%m"
+    }
+    else if span > 2.0 * DAY
+    {
+        "%Y-%m-%d"
+    }
+    else if span > 2.0 * 3600.0
+    {
+        "%m-%d %H:%M"
+    }
+    else if span > 120.0
+    {
+        "%H:%M"
+    }
+    else
+    {
+        "%H:%M:%S%.3f"
+    }
+}
+
+/// Linear axis over epoch seconds with calendar ticks and date labels
+pub struct TimeAxis
+{
+    seconds: RangedCoordf64,
+    dates: RangedDateTime<NaiveDateTime>,
+    format: String,
+}
+
+impl TimeAxis
+{
+    /// `format` is a strftime format for the labels, default depends on the span
+    pub fn new(range: Range<f64>, format: Option<&str>) -> TimeAxis
+    {
+        let format = format
+            .unwrap_or_else(|| default_format(range.end - range.start))
+            .to_string();
+        TimeAxis {
+            dates: (to_datetime(range.start)..to_datetime(range.end)).into(),
+            seconds: range.into(),
+            format,
+        }
+    }
+}
+
+impl Ranged for TimeAxis
+{
+    type FormatOption = NoDefaultFormatting;
+    type ValueType = f64;
+
+    fn map(&self, value: &f64, limit: (i32, i32)) -> i32
+    {
+        self.seconds.map(value, limit)
+    }
+
+    fn key_points<Hint: KeyPointHint>(&self, hint: Hint) -> Vec<f64>
+    {
+        self.dates
+            .key_points(hint)
+            .into_iter()
+            .map(|t| nanos_to_seconds(t.and_utc().timestamp_nanos_opt()))
+            .collect()
+    }
+
+    fn range(&self) -> Range<f64>
+    {
+        self.seconds.range()
+    }
+}
+
+impl ValueFormatter<f64> for TimeAxis
+{
+    fn format_ext(&self, value: &f64) -> String
+    {
+        to_datetime(*value).format(&self.format).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn seconds(series: Series, parse: &TimeParse) -> Vec<Option<f64>>
+    {
+        epoch_seconds(&series, parse)
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    /// 2024-01-02 03:04:05 UTC
+    const SECONDS: f64 = 1704164645.0;
+
+    #[test]
+    fn epoch_units_by_magnitude()
+    {
+        for (value, unit) in [(SECONDS, 1.0), (SECONDS * 1e3, 1e3), (SECONDS * 1e6, 1e6)]
+        {
+            let parsed = seconds(Series::new("t".into(), [value, value + unit]), &TimeParse::Auto);
+            assert!((parsed[0].unwrap() - SECONDS).abs() < 1e-3, "{} as {:?}", value, parsed);
+            assert!((parsed[1].unwrap() - SECONDS - 1.0).abs() < 1e-3);
+        }
+        let nanos = Series::new("t".into(), [SECONDS as i64 * 1_000_000_000]);
+        assert_eq!(seconds(nanos, &TimeParse::Auto), [Some(SECONDS)]);
+        // explicit units win over the magnitude
+        let millis = Series::new("t".into(), [1500]);
+        assert_eq!(seconds(millis.clone(), &TimeParse::EpochMillis), [Some(1.5)]);
+        assert_eq!(seconds(millis, &TimeParse::EpochSeconds), [Some(1500.0)]);
+    }
+
+    #[test]
+    fn auto_formats()
+    {
+        let parse = |value: &str| seconds(Series::new("t".into(), [value]), &TimeParse::Auto)[0];
+        assert_eq!(parse("2024-01-02 03:04:05"), Some(SECONDS));
+        assert_eq!(parse("2024-01-02T03:04:05.5"), Some(SECONDS + 0.5));
+        assert_eq!(parse("2024-01-02T04:04:05+01:00"), Some(SECONDS));
+        assert_eq!(parse("2024/01/02 03:04"), Some(SECONDS - 5.0));
+        assert_eq!(parse("2024-01-02"), Some(SECONDS - 11045.0));
+        assert_eq!(parse("02.01.2024"), Some(SECONDS - 11045.0));
+
+        // the first value decides the format, others that do not match become null
+        let mixed = Series::new("t".into(), ["2024-01-02", "02.01.2024", "2024-01-03"]);
+        assert_eq!(
+            seconds(mixed, &TimeParse::Auto),
+            [
+                Some(SECONDS - 11045.0),
+                None,
+                Some(SECONDS - 11045.0 + 86400.0)
+            ]
+        );
+        let unknown = Series::new("t".into(), ["Jan 2nd"]);
+        assert!(epoch_seconds(&unknown, &TimeParse::Auto).is_err());
+
+        let format = TimeParse::Format("%d/%m/%Y %H:%M".to_string());
+        assert_eq!(
+            seconds(Series::new("t".into(), ["02/01/2024 03:04"]), &format),
+            [Some(SECONDS - 5.0)]
+        );
+    }
+
+    #[test]
+    fn label_formats()
+    {
+        assert!(check_format("%Y-%m-%d %H:%M").is_ok());
+        assert!(check_format("%Q").is_err());
+        assert!(check_format("%Y-%").is_err());
+    }
+}